@@ -36,10 +36,43 @@ impl FromStr for Angles {
 }
 
 impl Angles {
+    /// The rotation in map coordinates, with x forward, y left and z up
+    ///
+    /// Angles are applied in roll (x), pitch (y), yaw (z) order, matching the engine
+    pub fn rotation(&self) -> Quaternion<f32> {
+        Quaternion::from_angle_z(Deg(self.yaw))
+            * Quaternion::from_angle_y(Deg(self.pitch))
+            * Quaternion::from_angle_x(Deg(self.roll))
+    }
+
+    /// The rotation in y-up coordinates, where `(x, y, z)` in map coordinates becomes `(y, z, x)`
+    ///
+    /// This is the same rotation as [`Angles::rotation`] with the axes permuted, yaw rotates
+    /// around y, pitch around x and roll around z.
     pub fn as_quaternion(&self) -> Quaternion<f32> {
-        // angles are applied in roll, pitch, yaw order
-        Quaternion::from_angle_y(Deg(self.yaw))
-            * Quaternion::from_angle_x(Deg(self.pitch))
-            * Quaternion::from_angle_z(Deg(self.roll))
+        let rotation = self.rotation();
+        Quaternion::new(rotation.s, rotation.v.y, rotation.v.z, rotation.v.x)
     }
 }
+
+#[test]
+fn test_angles_rotation_spaces() {
+    use cgmath::{InnerSpace, Rotation, Vector3};
+
+    let angles = Angles {
+        pitch: 30.0,
+        yaw: 120.0,
+        roll: -45.0,
+    };
+    let y_up = Quaternion::from_angle_y(Deg(angles.yaw))
+        * Quaternion::from_angle_x(Deg(angles.pitch))
+        * Quaternion::from_angle_z(Deg(angles.roll));
+    let quaternion = angles.as_quaternion();
+    assert!((quaternion.s - y_up.s).abs() < 0.0001);
+    assert!((quaternion.v - y_up.v).magnitude2() < 0.0001);
+
+    let point = Vector3::new(1.0, 2.0, 3.0);
+    let map = angles.rotation().rotate_vector(point);
+    let permuted = quaternion.rotate_vector(Vector3::new(point.y, point.z, point.x));
+    assert!((Vector3::new(map.y, map.z, map.x) - permuted).magnitude2() < 0.0001);
+}
@@ -0,0 +1,128 @@
+use crate::EntityParseError;
+use serde::de::{Error, Unexpected};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Separator used by newer versions of the engine, allowing commas in the parameter
+const ESCAPE_SEPARATOR: char = '\x1b';
+
+/// The value of an entity output, `target,input,parameter,delay,times_to_fire`
+///
+/// Newer maps separate the fields with `\x1b` instead of commas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityConnection {
+    pub target: String,
+    pub input: String,
+    pub parameter: String,
+    pub delay: f32,
+    /// The number of times the output fires, `None` if there is no limit
+    pub times_to_fire: Option<u32>,
+}
+
+impl FromStr for EntityConnection {
+    type Err = EntityParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = if s.contains(ESCAPE_SEPARATOR) {
+            ESCAPE_SEPARATOR
+        } else {
+            ','
+        };
+
+        // the parameter is taken from the middle so it can contain the separator in the old format
+        let mut start = s.splitn(3, separator);
+        let target = start.next().ok_or(EntityParseError::ElementCount)?;
+        let input = start.next().ok_or(EntityParseError::ElementCount)?;
+        let rest = start.next().ok_or(EntityParseError::ElementCount)?;
+        let mut end = rest.rsplitn(3, separator);
+        let times_to_fire = end.next().ok_or(EntityParseError::ElementCount)?;
+        let delay = end.next().ok_or(EntityParseError::ElementCount)?;
+        let parameter = end.next().ok_or(EntityParseError::ElementCount)?;
+
+        let delay = match delay.trim() {
+            "" => 0.0,
+            delay => delay.parse()?,
+        };
+        let times_to_fire = match times_to_fire.trim() {
+            "" => None,
+            times => Some(times.parse::<i32>()?).and_then(|times| u32::try_from(times).ok()),
+        };
+
+        Ok(EntityConnection {
+            target: target.into(),
+            input: input.into(),
+            parameter: parameter.into(),
+            delay,
+            times_to_fire,
+        })
+    }
+}
+
+impl fmt::Display for EntityConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if [&self.target, &self.input, &self.parameter]
+            .iter()
+            .any(|field| field.contains(','))
+        {
+            ESCAPE_SEPARATOR
+        } else {
+            ','
+        };
+        let times = self.times_to_fire.map(|times| times as i64).unwrap_or(-1);
+        write!(
+            f,
+            "{target}{separator}{input}{separator}{parameter}{separator}{delay}{separator}{times}",
+            target = self.target,
+            input = self.input,
+            parameter = self.parameter,
+            delay = self.delay,
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityConnection {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = <&str>::deserialize(deserializer)?;
+        str.parse()
+            .map_err(|_| D::Error::invalid_value(Unexpected::Other(str), &"an entity connection"))
+    }
+}
+
+#[test]
+fn test_parse_connection() {
+    let connection: EntityConnection = "door1,Open,,0,-1".parse().unwrap();
+    assert_eq!(
+        EntityConnection {
+            target: "door1".into(),
+            input: "Open".into(),
+            parameter: "".into(),
+            delay: 0.0,
+            times_to_fire: None,
+        },
+        connection
+    );
+    assert_eq!("door1,Open,,0,-1", connection.to_string());
+
+    let connection: EntityConnection = "!activator\x1bRunScriptCode\x1bfoo(1, 2)\x1b0.5\x1b1"
+        .parse()
+        .unwrap();
+    assert_eq!("!activator", connection.target);
+    assert_eq!("RunScriptCode", connection.input);
+    assert_eq!("foo(1, 2)", connection.parameter);
+    assert_eq!(0.5, connection.delay);
+    assert_eq!(Some(1), connection.times_to_fire);
+    assert_eq!(
+        "!activator\x1bRunScriptCode\x1bfoo(1, 2)\x1b0.5\x1b1",
+        connection.to_string()
+    );
+
+    let connection: EntityConnection = "relay,AddOutput,targetname,foo,2,1".parse().unwrap();
+    assert_eq!("targetname,foo", connection.parameter);
+    assert_eq!(2.0, connection.delay);
+
+    assert!("door1,Open".parse::<EntityConnection>().is_err());
+}
@@ -1,6 +1,7 @@
 mod angle;
 mod bool;
 mod color;
+mod connection;
 mod lightcolor;
 mod negated;
 mod prop;
@@ -10,6 +11,7 @@ mod vector;
 pub use angle::Angles;
 pub use bool::deserialize_bool;
 pub use color::Color;
+pub use connection::EntityConnection;
 pub use lightcolor::LightColor;
 pub use negated::Negated;
 pub use prop::{AsPropPlacement, PropPlacement};
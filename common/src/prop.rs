@@ -4,6 +4,7 @@ use cgmath::Quaternion;
 #[derive(Debug, Clone)]
 pub struct PropPlacement<'a> {
     pub model: &'a str,
+    /// The rotation in y-up coordinates, see [`Angles::as_quaternion`](crate::Angles::as_quaternion)
     pub rotation: Quaternion<f32>,
     pub scale: f32,
     pub origin: Vector,
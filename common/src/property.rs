@@ -1,4 +1,4 @@
-use crate::{Angles, Color, LightColor, Negated, Vector};
+use crate::{Angles, Color, EntityConnection, LightColor, Negated, Vector};
 use std::num::{ParseFloatError, ParseIntError};
 use std::str::FromStr;
 use thiserror::Error;
@@ -20,6 +20,7 @@ impl FromStrProp for Angles {}
 impl FromStrProp for Vector {}
 impl FromStrProp for LightColor {}
 impl FromStrProp for Negated {}
+impl FromStrProp for EntityConnection {}
 
 impl<T: FromStrProp> EntityProp<'_> for T
 where
@@ -23,6 +23,18 @@ impl Vector {
     pub fn length_squared(&self) -> f32 {
         self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)
     }
+
+    pub fn dot(&self, other: Vector) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: Vector) -> Vector {
+        Vector {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
 }
 
 impl Add<Vector> for Vector {
@@ -0,0 +1,20 @@
+use main_error::MainError;
+
+fn main() -> Result<(), MainError> {
+    let mut args = std::env::args();
+    let bin = args.next().unwrap();
+    let (Some(file), Some(target)) = (args.next(), args.next()) else {
+        eprintln!("usage: {bin} <map.bsp> <target directory> [pattern]");
+        return Ok(());
+    };
+    let data = std::fs::read(file)?;
+    let bsp = vbsp::Bsp::read(&data)?;
+
+    let count = match args.next() {
+        Some(pattern) => bsp.pack.extract_matching_to(&target, &pattern)?,
+        None => bsp.pack.extract_to(&target)?,
+    };
+    println!("extracted {count} files to {target}");
+
+    Ok(())
+}
@@ -0,0 +1,342 @@
+use crate::{Fgd, FgdClass, FgdProperty, PropertyType};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield",
+];
+
+/// Generate rust source for typed entities from the entity classes in an fgd
+///
+/// The generated code contains an `Entity` enum with a variant for every entity class, which can be
+/// used with `RawEntity::parse`, and a struct for every class with its (inherited) properties.
+/// All properties are optional since the compiler doesn't store properties that are left empty.
+/// Inputs, outputs and spawnflags are available as constants on the struct.
+///
+/// The generated code refers to the `vbsp` and `serde` crates, it's intended to be written from a
+/// build script and included with
+/// `include!(concat!(env!("OUT_DIR"), "/entities.rs"))`
+pub fn generate(fgd: &Fgd) -> String {
+    let mut structs = Vec::new();
+    let mut names = HashSet::new();
+    for class in fgd.entity_classes() {
+        let mut name = pascal_case(&class.name);
+        while !names.insert(name.clone()) {
+            name.push('_');
+        }
+        structs.push(EntityStruct::new(fgd, class, name));
+    }
+    let borrowed = structs.iter().any(|entity| entity.borrowed);
+    let lifetime = if borrowed { "<'a>" } else { "" };
+
+    let mut out = String::new();
+    out.push_str("// generated by vbsp-fgd, do not edit\n\n");
+    out.push_str("#[derive(Debug, Clone, ::serde::Deserialize)]\n");
+    out.push_str("#[non_exhaustive]\n");
+    out.push_str("#[serde(tag = \"classname\")]\n");
+    writeln!(out, "pub enum Entity{lifetime} {{").unwrap();
+    for entity in &structs {
+        writeln!(
+            out,
+            "    #[serde(rename = {:?})]",
+            entity.class.name.to_ascii_lowercase()
+        )
+        .unwrap();
+        if entity.borrowed {
+            out.push_str("    #[serde(borrow)]\n");
+        }
+        writeln!(
+            out,
+            "    {name}({name}{lifetime}),",
+            name = entity.name,
+            lifetime = entity.lifetime()
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+
+    for entity in &structs {
+        out.push('\n');
+        entity.write(&mut out);
+    }
+
+    out.push_str(
+        "\n#[allow(dead_code)]\n\
+        fn deserialize_optional_bool<'de, D: ::serde::Deserializer<'de>>(\n    \
+            deserializer: D,\n\
+        ) -> Result<Option<bool>, D::Error> {\n    \
+            ::vbsp::deserialize_bool(deserializer).map(Some)\n\
+        }\n",
+    );
+    out
+}
+
+struct EntityStruct<'a> {
+    class: &'a FgdClass,
+    name: String,
+    fields: Vec<Field<'a>>,
+    inputs: Vec<&'a str>,
+    outputs: Vec<&'a str>,
+    flags: Vec<(String, u32)>,
+    borrowed: bool,
+}
+
+struct Field<'a> {
+    name: String,
+    property: &'a FgdProperty,
+    ty: String,
+}
+
+impl<'a> EntityStruct<'a> {
+    fn new(fgd: &'a Fgd, class: &'a FgdClass, name: String) -> Self {
+        let mut fields = Vec::new();
+        let mut field_names = HashSet::new();
+        let mut flags = Vec::new();
+        let mut flag_names = HashSet::new();
+
+        for property in fgd.properties(class) {
+            let key = property.name.to_ascii_lowercase();
+            // the classname is used as the enum tag
+            if key == "classname" {
+                continue;
+            }
+            let name = field_name(&key);
+            if !field_names.insert(name.clone()) {
+                continue;
+            }
+            if property.ty == PropertyType::Flags {
+                for choice in &property.choices {
+                    let Ok(value) = choice.value.parse::<u32>() else {
+                        continue;
+                    };
+                    let mut flag = format!(
+                        "{}_{}",
+                        constant_name(&property.name),
+                        constant_name(&choice.name)
+                    );
+                    while !flag_names.insert(flag.clone()) {
+                        flag.push('_');
+                    }
+                    flags.push((flag, value));
+                }
+            }
+            fields.push(Field {
+                name,
+                property,
+                ty: field_type(property),
+            });
+        }
+        let borrowed = fields.iter().any(|field| field.ty.contains("'a"));
+
+        EntityStruct {
+            class,
+            name,
+            fields,
+            inputs: fgd
+                .inputs(class)
+                .iter()
+                .map(|io| io.name.as_str())
+                .collect(),
+            outputs: fgd
+                .outputs(class)
+                .iter()
+                .map(|io| io.name.as_str())
+                .collect(),
+            flags,
+            borrowed,
+        }
+    }
+
+    fn lifetime(&self) -> &'static str {
+        if self.borrowed {
+            "<'a>"
+        } else {
+            ""
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        if let Some(description) = &self.class.description {
+            writeln!(out, "#[doc = {:?}]", description).unwrap();
+        }
+        out.push_str("#[derive(Debug, Clone, ::serde::Deserialize)]\n");
+        writeln!(out, "pub struct {}{} {{", self.name, self.lifetime()).unwrap();
+        for field in &self.fields {
+            let property = field.property;
+            let doc = match (&property.display_name, &property.description) {
+                (Some(name), Some(description)) => Some(format!("{name}: {description}")),
+                (Some(doc), None) | (None, Some(doc)) => Some(doc.clone()),
+                (None, None) => None,
+            };
+            if let Some(doc) = doc {
+                writeln!(out, "    #[doc = {:?}]", doc).unwrap();
+            }
+            let key = property.name.to_ascii_lowercase();
+            if field.name.trim_start_matches("r#") != key {
+                writeln!(out, "    #[serde(rename = {:?})]", key).unwrap();
+            }
+            if property.ty == PropertyType::Boolean {
+                out.push_str(
+                    "    #[serde(default, deserialize_with = \"deserialize_optional_bool\")]\n",
+                );
+            } else {
+                out.push_str("    #[serde(default)]\n");
+            }
+            writeln!(out, "    pub {}: Option<{}>,", field.name, field.ty).unwrap();
+        }
+        out.push_str("}\n\n");
+
+        let lifetime = if self.borrowed { "<'_>" } else { "" };
+        // the lifetimes can't be elided in an impl for a type with a lifetime
+        out.push_str("#[allow(clippy::redundant_static_lifetimes)]\n");
+        writeln!(out, "impl {}{} {{", self.name, lifetime).unwrap();
+        writeln!(
+            out,
+            "    pub const CLASSNAME: &'static str = {:?};",
+            self.class.name
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "    pub const INPUTS: &'static [&'static str] = &{:?};",
+            self.inputs
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "    pub const OUTPUTS: &'static [&'static str] = &{:?};",
+            self.outputs
+        )
+        .unwrap();
+        for (name, value) in &self.flags {
+            writeln!(out, "    pub const {name}: u32 = {value};").unwrap();
+        }
+        out.push_str("}\n");
+    }
+}
+
+fn field_type(property: &FgdProperty) -> String {
+    match property.ty {
+        PropertyType::Integer => "i32",
+        PropertyType::Float => "f32",
+        PropertyType::Boolean => "bool",
+        PropertyType::Flags => "u32",
+        PropertyType::Choices
+            if !property.choices.is_empty()
+                && property
+                    .choices
+                    .iter()
+                    .all(|choice| choice.value.parse::<i32>().is_ok()) =>
+        {
+            "i32"
+        }
+        // colors can have a 4th brightness component, which maps don't use consistently for the same
+        // property, so they're left for the user to parse as a `Color` or `LightColor`
+        PropertyType::Origin | PropertyType::Vector | PropertyType::VecLine => "::vbsp::Vector",
+        PropertyType::Angle => "::vbsp::Angles",
+        _ => "&'a str",
+    }
+    .into()
+}
+
+/// Turn a property key into a valid field name
+fn field_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || name == "_" {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.insert_str(0, "r#");
+    }
+    name
+}
+
+fn pascal_case(name: &str) -> String {
+    let mut result = String::new();
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            result.push(first.to_ascii_uppercase());
+            result.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+fn constant_name(name: &str) -> String {
+    let mut result = String::new();
+    for part in name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+    {
+        if !result.is_empty() {
+            result.push('_');
+        }
+        result.push_str(&part.to_ascii_uppercase());
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+#[test]
+fn test_generate() {
+    let fgd = Fgd::parse(
+        r#"
+@BaseClass = Targetname
+[
+    targetname(target_source) : "Name"
+    input Kill(void) : "Removes this entity from the world."
+]
+@SolidClass base(Targetname) = func_door : "A door"
+[
+    speed(integer) : "Speed" : 100
+    startdisabled(boolean) : "Start Disabled" : 0
+    type(choices) : "Type" : 0 = [ 0 : "A" 1 : "B" ]
+    _light(color255) : "Brightness" : "255 255 255 200"
+    spawnflags(flags) =
+    [
+        1 : "Starts Open" : 0
+        2048 : "Starts locked" : 0
+    ]
+    output OnOpen(void) : "Fired when the door opens"
+]
+@PointClass = env_sun
+[
+    angles(angle) : "Pitch Yaw Roll (Y Z X)" : "0 0 0"
+]
+"#,
+    )
+    .unwrap();
+    let code = generate(&fgd);
+
+    assert!(code.contains("pub enum Entity<'a> {"));
+    assert!(code.contains(
+        "    #[serde(rename = \"func_door\")]\n    #[serde(borrow)]\n    FuncDoor(FuncDoor<'a>),"
+    ));
+    assert!(code.contains("    #[serde(rename = \"env_sun\")]\n    EnvSun(EnvSun),"));
+    assert!(code.contains("#[doc = \"A door\"]\n#[derive(Debug, Clone, ::serde::Deserialize)]\npub struct FuncDoor<'a> {"));
+    assert!(code.contains("    pub targetname: Option<&'a str>,"));
+    assert!(code.contains("    pub speed: Option<i32>,"));
+    assert!(code.contains("    pub r#type: Option<i32>,"));
+    assert!(code.contains("    #[serde(default, deserialize_with = \"deserialize_optional_bool\")]\n    pub startdisabled: Option<bool>,"));
+    assert!(code.contains("    pub _light: Option<&'a str>,"));
+    assert!(code.contains("    pub spawnflags: Option<u32>,"));
+    assert!(code.contains("    pub const INPUTS: &'static [&'static str] = &[\"Kill\"];"));
+    assert!(code.contains("    pub const OUTPUTS: &'static [&'static str] = &[\"OnOpen\"];"));
+    assert!(code.contains("    pub const SPAWNFLAGS_STARTS_LOCKED: u32 = 2048;"));
+    assert!(code.contains("pub struct EnvSun {\n"));
+    assert!(code.contains("    pub angles: Option<::vbsp::Angles>,"));
+    assert!(!code.contains("pub struct Targetname"));
+}
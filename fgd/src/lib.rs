@@ -0,0 +1,284 @@
+mod codegen;
+mod parser;
+
+pub use codegen::generate;
+pub use parser::FgdError;
+
+use std::path::Path;
+
+/// The entity definitions from a hammer fgd file and all files it includes
+#[derive(Debug, Clone, Default)]
+pub struct Fgd {
+    pub classes: Vec<FgdClass>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FgdClass {
+    pub kind: ClassKind,
+    pub name: String,
+    pub description: Option<String>,
+    /// Names of the classes this class inherits from, from the `base(...)` helper
+    pub bases: Vec<String>,
+    /// All other helpers like `studio(...)` or `size(...)`, with their raw arguments
+    pub helpers: Vec<FgdHelper>,
+    pub properties: Vec<FgdProperty>,
+    pub inputs: Vec<FgdIo>,
+    pub outputs: Vec<FgdIo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassKind {
+    /// `@BaseClass`, only used for inheritance
+    Base,
+    Point,
+    Solid,
+    Npc,
+    KeyFrame,
+    Move,
+    Filter,
+    /// Any other `@...Class`
+    Other(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FgdHelper {
+    pub name: String,
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FgdProperty {
+    pub name: String,
+    pub ty: PropertyType,
+    pub display_name: Option<String>,
+    pub default: Option<String>,
+    pub description: Option<String>,
+    pub read_only: bool,
+    /// The options for `choices` and `flags` properties
+    pub choices: Vec<FgdChoice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Choices,
+    Flags,
+    Color255,
+    Color1,
+    Origin,
+    Vector,
+    VecLine,
+    Angle,
+    TargetSource,
+    TargetDestination,
+    Studio,
+    Sprite,
+    Sound,
+    Material,
+    Decal,
+    /// Any other type, these are treated as strings
+    Other(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FgdChoice {
+    pub value: String,
+    pub name: String,
+    /// Whether a flag is enabled by default, only set for `flags` properties
+    pub default: Option<bool>,
+}
+
+/// An entity input or output
+#[derive(Debug, Clone)]
+pub struct FgdIo {
+    pub name: String,
+    /// The parameter type, `void` if the io has no parameter
+    pub ty: String,
+    pub description: Option<String>,
+}
+
+impl ClassKind {
+    fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "baseclass" => ClassKind::Base,
+            "pointclass" => ClassKind::Point,
+            "solidclass" => ClassKind::Solid,
+            "npcclass" => ClassKind::Npc,
+            "keyframeclass" => ClassKind::KeyFrame,
+            "moveclass" => ClassKind::Move,
+            "filterclass" => ClassKind::Filter,
+            _ => ClassKind::Other(name.into()),
+        }
+    }
+}
+
+impl PropertyType {
+    fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "string" => PropertyType::String,
+            "integer" => PropertyType::Integer,
+            "float" => PropertyType::Float,
+            "boolean" => PropertyType::Boolean,
+            "choices" => PropertyType::Choices,
+            "flags" => PropertyType::Flags,
+            "color255" => PropertyType::Color255,
+            "color1" => PropertyType::Color1,
+            "origin" => PropertyType::Origin,
+            "vector" => PropertyType::Vector,
+            "vecline" => PropertyType::VecLine,
+            "angle" => PropertyType::Angle,
+            "target_source" => PropertyType::TargetSource,
+            "target_destination" => PropertyType::TargetDestination,
+            "studio" => PropertyType::Studio,
+            "sprite" => PropertyType::Sprite,
+            "sound" => PropertyType::Sound,
+            "material" => PropertyType::Material,
+            "decal" => PropertyType::Decal,
+            _ => PropertyType::Other(name.into()),
+        }
+    }
+}
+
+impl Fgd {
+    /// Parse an fgd file, `@include` isn't supported without a way to load the included files
+    pub fn parse(source: &str) -> Result<Self, FgdError> {
+        Fgd::parse_with_includes(source, |name| Err(FgdError::Include(name.into())))
+    }
+
+    /// Parse an fgd file, loading the contents of `@include`d files with `load`
+    pub fn parse_with_includes(
+        source: &str,
+        mut load: impl FnMut(&str) -> Result<String, FgdError>,
+    ) -> Result<Self, FgdError> {
+        let mut fgd = Fgd::default();
+        parser::parse(source, &mut fgd, &mut load, 0)?;
+        Ok(fgd)
+    }
+
+    /// Load an fgd file, included files are loaded relative to the directory of the file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FgdError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let source = std::fs::read_to_string(path)?;
+        Fgd::parse_with_includes(&source, |name| {
+            Ok(std::fs::read_to_string(directory.join(name))?)
+        })
+    }
+
+    /// Get a class by name, matched case-insensitively
+    pub fn class(&self, name: &str) -> Option<&FgdClass> {
+        self.classes
+            .iter()
+            .find(|class| class.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Iterate over all classes that can be placed in a map
+    pub fn entity_classes(&self) -> impl Iterator<Item = &FgdClass> {
+        self.classes
+            .iter()
+            .filter(|class| class.kind != ClassKind::Base)
+    }
+
+    /// Get all properties of a class, including the inherited ones
+    ///
+    /// Inherited properties come first, properties redefined by a class replace the inherited definition.
+    pub fn properties<'a>(&'a self, class: &'a FgdClass) -> Vec<&'a FgdProperty> {
+        self.collect(class, &|class| &class.properties, &mut Vec::new())
+    }
+
+    /// Get all inputs of a class, including the inherited ones
+    pub fn inputs<'a>(&'a self, class: &'a FgdClass) -> Vec<&'a FgdIo> {
+        self.collect(class, &|class| &class.inputs, &mut Vec::new())
+    }
+
+    /// Get all outputs of a class, including the inherited ones
+    pub fn outputs<'a>(&'a self, class: &'a FgdClass) -> Vec<&'a FgdIo> {
+        self.collect(class, &|class| &class.outputs, &mut Vec::new())
+    }
+
+    fn collect<'a, T: Named>(
+        &'a self,
+        class: &'a FgdClass,
+        items: &impl Fn(&'a FgdClass) -> &'a [T],
+        visited: &mut Vec<&'a str>,
+    ) -> Vec<&'a T> {
+        // guard against inheritance loops
+        if visited.contains(&class.name.as_str()) {
+            return Vec::new();
+        }
+        visited.push(&class.name);
+
+        let mut result: Vec<&T> = Vec::new();
+        let bases = class.bases.iter().filter_map(|base| self.class(base));
+        for item in bases
+            .flat_map(|base| self.collect(base, items, visited))
+            .chain(items(class))
+        {
+            match result
+                .iter_mut()
+                .find(|existing| existing.name().eq_ignore_ascii_case(item.name()))
+            {
+                Some(existing) => *existing = item,
+                None => result.push(item),
+            }
+        }
+        result
+    }
+}
+
+trait Named {
+    fn name(&self) -> &str;
+}
+
+impl Named for FgdProperty {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Named for FgdIo {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[test]
+fn test_inheritance() {
+    let fgd = Fgd::parse(
+        r#"
+@BaseClass = Targetname
+[
+    targetname(target_source) : "Name"
+    input Kill(void) : "Removes this entity from the world."
+]
+@BaseClass base(Targetname) = Toggle
+[
+    targetname(target_source) : "Name" : "toggle"
+    StartDisabled(boolean) : "Start Disabled" : 0
+    input Enable(void) : "Enable"
+]
+@PointClass base(Toggle) = logic_relay
+[
+    output OnTrigger(void) : "Fired when the relay is triggered"
+]
+"#,
+    )
+    .unwrap();
+
+    let relay = fgd.class("LOGIC_RELAY").unwrap();
+    let properties = fgd.properties(relay);
+    assert_eq!(2, properties.len());
+    assert_eq!(Some("toggle"), properties[0].default.as_deref());
+    assert_eq!(
+        vec!["Kill", "Enable"],
+        fgd.inputs(relay)
+            .iter()
+            .map(|io| io.name.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, fgd.outputs(relay).len());
+    assert_eq!(1, fgd.entity_classes().count());
+}
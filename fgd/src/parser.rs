@@ -0,0 +1,537 @@
+use crate::{ClassKind, Fgd, FgdChoice, FgdClass, FgdHelper, FgdIo, FgdProperty, PropertyType};
+use std::collections::VecDeque;
+use thiserror::Error;
+
+/// Maximum depth of nested `@include`s
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum FgdError {
+    #[error("syntax error on line {line}: expected {expected}")]
+    Syntax { line: usize, expected: &'static str },
+    #[error("unterminated string on line {line}")]
+    UnterminatedString { line: usize },
+    #[error("unexpected end of file, expected {expected}")]
+    UnexpectedEnd { expected: &'static str },
+    #[error("can't include {0:?}")]
+    Include(String),
+    #[error("too many nested includes")]
+    IncludeDepth,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub(crate) fn parse(
+    source: &str,
+    fgd: &mut Fgd,
+    load: &mut dyn FnMut(&str) -> Result<String, FgdError>,
+    depth: usize,
+) -> Result<(), FgdError> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(FgdError::IncludeDepth);
+    }
+    let mut parser = Parser {
+        tokens: Tokenizer::new(source),
+        peeked: VecDeque::new(),
+    };
+
+    while let Some(token) = parser.next()? {
+        if token.kind != TokenKind::At {
+            return Err(FgdError::Syntax {
+                line: token.line,
+                expected: "a declaration starting with @",
+            });
+        }
+        let name = parser.word("a declaration name")?;
+        if name.eq_ignore_ascii_case("include") {
+            let file = parser.string("the included file name")?;
+            let included = load(&file)?;
+            parse(&included, fgd, load, depth + 1)?;
+        } else if name.to_ascii_lowercase().ends_with("class") {
+            let class = parser.class(ClassKind::from_name(&name))?;
+            match fgd
+                .classes
+                .iter_mut()
+                .find(|existing| existing.name.eq_ignore_ascii_case(&class.name))
+            {
+                Some(existing) => *existing = class,
+                None => fgd.classes.push(class),
+            }
+        } else {
+            // @mapsize, @MaterialExclusion, @AutoVisGroup, etc
+            parser.skip_declaration()?;
+        }
+    }
+    Ok(())
+}
+
+struct Parser<'a> {
+    tokens: Tokenizer<'a>,
+    peeked: VecDeque<Token<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<Option<Token<'a>>, FgdError> {
+        match self.peeked.pop_front() {
+            Some(token) => Ok(Some(token)),
+            None => self.tokens.next(),
+        }
+    }
+
+    /// Look at the token `n` tokens ahead without consuming it
+    fn peek_kind_nth(&mut self, n: usize) -> Result<Option<TokenKind<'a>>, FgdError> {
+        while self.peeked.len() <= n {
+            match self.tokens.next()? {
+                Some(token) => self.peeked.push_back(token),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(self.peeked[n].kind.clone()))
+    }
+
+    fn peek_kind(&mut self) -> Result<Option<TokenKind<'a>>, FgdError> {
+        self.peek_kind_nth(0)
+    }
+
+    fn expect(&mut self, expected: &'static str) -> Result<Token<'a>, FgdError> {
+        self.next()?.ok_or(FgdError::UnexpectedEnd { expected })
+    }
+
+    fn expect_kind(&mut self, kind: TokenKind, expected: &'static str) -> Result<(), FgdError> {
+        let token = self.expect(expected)?;
+        if token.kind == kind {
+            Ok(())
+        } else {
+            Err(FgdError::Syntax {
+                line: token.line,
+                expected,
+            })
+        }
+    }
+
+    /// Consume the next token if it matches `kind`
+    fn eat(&mut self, kind: TokenKind) -> Result<bool, FgdError> {
+        if self.peek_kind()? == Some(kind) {
+            self.next()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn word(&mut self, expected: &'static str) -> Result<String, FgdError> {
+        let token = self.expect(expected)?;
+        match token.kind {
+            TokenKind::Word(word) => Ok(word.into()),
+            _ => Err(FgdError::Syntax {
+                line: token.line,
+                expected,
+            }),
+        }
+    }
+
+    /// Read a string, consisting of one or more quoted strings joined by `+`
+    fn string(&mut self, expected: &'static str) -> Result<String, FgdError> {
+        let token = self.expect(expected)?;
+        let TokenKind::String(first) = token.kind else {
+            return Err(FgdError::Syntax {
+                line: token.line,
+                expected,
+            });
+        };
+        let mut string = first.to_string();
+        while self.eat(TokenKind::Plus)? {
+            let token = self.expect("a string")?;
+            match token.kind {
+                TokenKind::String(part) => string.push_str(part),
+                _ => {
+                    return Err(FgdError::Syntax {
+                        line: token.line,
+                        expected: "a string",
+                    });
+                }
+            }
+        }
+        Ok(string)
+    }
+
+    /// Read a quoted string or bare word as value
+    fn value(&mut self, expected: &'static str) -> Result<String, FgdError> {
+        match self.peek_kind()? {
+            Some(TokenKind::String(_)) => self.string(expected),
+            _ => self.word(expected),
+        }
+    }
+
+    /// Read an optional `: value` section, returning `None` if the section is missing or empty
+    ///
+    /// Only quoted strings are accepted as value unless `allow_word` is set, a word followed by `(`
+    /// is never accepted since it starts the next property.
+    fn optional_section(
+        &mut self,
+        expected: &'static str,
+        allow_word: bool,
+    ) -> Result<Option<String>, FgdError> {
+        if !self.eat(TokenKind::Colon)? {
+            return Ok(None);
+        }
+        let has_value = match self.peek_kind()? {
+            Some(TokenKind::String(_)) => true,
+            Some(TokenKind::Word(_)) => {
+                allow_word && self.peek_kind_nth(1)? != Some(TokenKind::OpenParen)
+            }
+            _ => false,
+        };
+        if !has_value {
+            return Ok(None);
+        }
+        let value = self.value(expected)?;
+        Ok(Some(value).filter(|value| !value.is_empty()))
+    }
+
+    fn class(&mut self, kind: ClassKind) -> Result<FgdClass, FgdError> {
+        let mut bases = Vec::new();
+        let mut helpers = Vec::new();
+        while !self.eat(TokenKind::Equals)? {
+            let name = self.word("a class helper or =")?;
+            let arguments = self.helper_arguments()?;
+            if name.eq_ignore_ascii_case("base") {
+                bases.extend(arguments);
+            } else {
+                helpers.push(FgdHelper { name, arguments });
+            }
+        }
+        let name = self.word("the class name")?;
+        let description = if self.eat(TokenKind::Colon)? {
+            Some(self.string("the class description")?)
+        } else {
+            None
+        };
+
+        let mut class = FgdClass {
+            kind,
+            name,
+            description,
+            bases,
+            helpers,
+            properties: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+
+        self.expect_kind(TokenKind::OpenBracket, "[")?;
+        while !self.eat(TokenKind::CloseBracket)? {
+            let name = self.word("a property, input or output")?;
+            let is_io = name.eq_ignore_ascii_case("input") || name.eq_ignore_ascii_case("output");
+            if is_io && self.peek_kind()? != Some(TokenKind::OpenParen) {
+                let io = self.io()?;
+                if name.eq_ignore_ascii_case("input") {
+                    class.inputs.push(io);
+                } else {
+                    class.outputs.push(io);
+                }
+            } else {
+                class.properties.push(self.property(name)?);
+            }
+        }
+        Ok(class)
+    }
+
+    /// Read the comma separated arguments of a helper, if the helper has any
+    fn helper_arguments(&mut self) -> Result<Vec<String>, FgdError> {
+        let mut arguments = Vec::new();
+        if !self.eat(TokenKind::OpenParen)? {
+            return Ok(arguments);
+        }
+        let mut current = Vec::new();
+        loop {
+            let token = self.expect(")")?;
+            match token.kind {
+                TokenKind::CloseParen | TokenKind::Comma => {
+                    if !current.is_empty() {
+                        arguments.push(current.join(" "));
+                        current.clear();
+                    }
+                    if token.kind == TokenKind::CloseParen {
+                        return Ok(arguments);
+                    }
+                }
+                TokenKind::Word(word) | TokenKind::String(word) => current.push(word),
+                _ => {
+                    return Err(FgdError::Syntax {
+                        line: token.line,
+                        expected: "a helper argument",
+                    });
+                }
+            }
+        }
+    }
+
+    fn io(&mut self) -> Result<FgdIo, FgdError> {
+        let name = self.word("the io name")?;
+        self.expect_kind(TokenKind::OpenParen, "(")?;
+        let ty = self.word("the io type")?;
+        self.expect_kind(TokenKind::CloseParen, ")")?;
+        let description = self.optional_section("the io description", false)?;
+        Ok(FgdIo {
+            name,
+            ty,
+            description,
+        })
+    }
+
+    fn property(&mut self, name: String) -> Result<FgdProperty, FgdError> {
+        self.expect_kind(TokenKind::OpenParen, "(")?;
+        let ty = PropertyType::from_name(&self.word("the property type")?);
+        self.expect_kind(TokenKind::CloseParen, ")")?;
+
+        let mut read_only = false;
+        while let Some(TokenKind::Word(modifier)) = self.peek_kind()? {
+            if modifier.eq_ignore_ascii_case("readonly") {
+                read_only = true;
+            } else if !modifier.eq_ignore_ascii_case("report") {
+                break;
+            }
+            self.next()?;
+        }
+
+        let display_name = self.optional_section("the property name", false)?;
+        let default = self.optional_section("the property default", true)?;
+        let description = self.optional_section("the property description", false)?;
+
+        let mut choices = Vec::new();
+        if self.eat(TokenKind::Equals)? {
+            self.expect_kind(TokenKind::OpenBracket, "[")?;
+            while !self.eat(TokenKind::CloseBracket)? {
+                let value = self.value("a choice value")?;
+                self.expect_kind(TokenKind::Colon, ":")?;
+                let name = self.string("the choice name")?;
+                let default = if ty == PropertyType::Flags {
+                    Some(self.optional_section("the flag default", true)?.as_deref() == Some("1"))
+                } else {
+                    None
+                };
+                choices.push(FgdChoice {
+                    value,
+                    name,
+                    default,
+                });
+            }
+        }
+
+        Ok(FgdProperty {
+            name,
+            ty,
+            display_name,
+            default,
+            description,
+            read_only,
+            choices,
+        })
+    }
+
+    /// Skip a declaration we don't handle, up to and including its bracketed body
+    fn skip_declaration(&mut self) -> Result<(), FgdError> {
+        let mut depth = 0;
+        loop {
+            match self.peek_kind()? {
+                None if depth == 0 => return Ok(()),
+                None => return Err(FgdError::UnexpectedEnd { expected: "]" }),
+                Some(TokenKind::At) if depth == 0 => return Ok(()),
+                Some(TokenKind::OpenBracket) => depth += 1,
+                Some(TokenKind::CloseBracket) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.next()?;
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+            self.next()?;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    kind: TokenKind<'a>,
+    line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind<'a> {
+    At,
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Equals,
+    Colon,
+    Comma,
+    Plus,
+    String(&'a str),
+    Word(&'a str),
+}
+
+struct Tokenizer<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(source: &'a str) -> Self {
+        Tokenizer {
+            rest: source,
+            line: 1,
+        }
+    }
+
+    fn advance(&mut self, count: usize) -> &'a str {
+        let (taken, rest) = self.rest.split_at(count);
+        self.line += taken.matches('\n').count();
+        self.rest = rest;
+        taken
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let trimmed = self.rest.trim_start();
+            self.advance(self.rest.len() - trimmed.len());
+            if trimmed.starts_with("//") {
+                self.advance(trimmed.find('\n').unwrap_or(trimmed.len()));
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, FgdError> {
+        self.skip_whitespace();
+        let line = self.line;
+        let Some(first) = self.rest.chars().next() else {
+            return Ok(None);
+        };
+        let punctuation = match first {
+            '@' => Some(TokenKind::At),
+            '(' => Some(TokenKind::OpenParen),
+            ')' => Some(TokenKind::CloseParen),
+            '[' => Some(TokenKind::OpenBracket),
+            ']' => Some(TokenKind::CloseBracket),
+            '=' => Some(TokenKind::Equals),
+            ':' => Some(TokenKind::Colon),
+            ',' => Some(TokenKind::Comma),
+            '+' => Some(TokenKind::Plus),
+            _ => None,
+        };
+        let kind = if let Some(kind) = punctuation {
+            self.advance(1);
+            kind
+        } else if first == '"' {
+            let end = self.rest[1..]
+                .find('"')
+                .ok_or(FgdError::UnterminatedString { line })?;
+            TokenKind::String(&self.advance(end + 2)[1..end + 1])
+        } else {
+            let end = self
+                .rest
+                .find(|c: char| c.is_whitespace() || "@()[]=:,+\"".contains(c))
+                .unwrap_or(self.rest.len());
+            TokenKind::Word(self.advance(end))
+        };
+        Ok(Some(Token { kind, line }))
+    }
+}
+
+#[test]
+fn test_parse_fgd() {
+    let fgd = Fgd::parse(
+        r#"
+// comment
+@mapsize(-16384, 16384)
+
+@MaterialExclusion
+[
+    "debug"
+]
+
+@PointClass base(Targetname, Angles) studio("models/editor/cone.mdl") size(-8 -8 -8, 8 8 8) color(0 0 255)
+    = prop_door_rotating : "An entity used to place a door in the world. " +
+    "Rotates around its origin."
+[
+    model(studio) readonly : "World Model"
+    skin(integer) : "Skin" : 0 : "Some models have multiple versions."
+    spawnpos(choices) : "Spawn Position" : 0 =
+    [
+        0 : "Closed"
+        1 : "Open forward"
+    ]
+    spawnflags(flags) =
+    [
+        1 : "Starts Open" : 0
+        2048 : "Starts locked" : 1
+    ]
+    distance(float) : "Rotation Distance" : 90 :
+    soundopenoverride(sound) : : "" : "Sound played when the door opens"
+
+    input Open(void) : "Open the door."
+    output OnOpen(void) : "Fired when the door is opened."
+]
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(1, fgd.classes.len());
+    let class = &fgd.classes[0];
+    assert_eq!(ClassKind::Point, class.kind);
+    assert_eq!("prop_door_rotating", class.name);
+    assert_eq!(
+        Some("An entity used to place a door in the world. Rotates around its origin."),
+        class.description.as_deref()
+    );
+    assert_eq!(vec!["Targetname", "Angles"], class.bases);
+    assert_eq!("studio", class.helpers[0].name);
+    assert_eq!(vec!["models/editor/cone.mdl"], class.helpers[0].arguments);
+    assert_eq!(vec!["-8 -8 -8", "8 8 8"], class.helpers[1].arguments);
+
+    assert_eq!(6, class.properties.len());
+    assert!(class.properties[0].read_only);
+    assert_eq!(PropertyType::Studio, class.properties[0].ty);
+    assert_eq!(Some("0"), class.properties[1].default.as_deref());
+    assert_eq!(2, class.properties[2].choices.len());
+    assert_eq!("Open forward", class.properties[2].choices[1].name);
+    assert_eq!(Some(true), class.properties[3].choices[1].default);
+    assert_eq!("2048", class.properties[3].choices[1].value);
+    assert_eq!(Some("90"), class.properties[4].default.as_deref());
+    assert_eq!(None, class.properties[5].display_name);
+    assert_eq!(
+        Some("Sound played when the door opens"),
+        class.properties[5].description.as_deref()
+    );
+
+    assert_eq!("Open", class.inputs[0].name);
+    assert_eq!("void", class.outputs[0].ty);
+}
+
+#[test]
+fn test_fgd_include() {
+    let fgd = Fgd::parse_with_includes(
+        "@include \"base.fgd\"\n@PointClass base(Targetname) = info_target []",
+        |name| {
+            assert_eq!("base.fgd", name);
+            Ok("@BaseClass = Targetname [ targetname(target_source) : \"Name\" ]".into())
+        },
+    )
+    .unwrap();
+    assert_eq!(2, fgd.classes.len());
+    assert_eq!(1, fgd.properties(&fgd.classes[1]).len());
+
+    assert!(matches!(
+        Fgd::parse("@include \"base.fgd\""),
+        Err(FgdError::Include(_))
+    ));
+    assert!(matches!(
+        Fgd::parse("@PointClass = info_target\n[\n    foo(string) :\n"),
+        Err(FgdError::UnexpectedEnd { .. })
+    ));
+}
@@ -0,0 +1,83 @@
+// generated by vbsp-fgd, do not edit
+
+#[derive(Debug, Clone, ::serde::Deserialize)]
+#[non_exhaustive]
+#[serde(tag = "classname")]
+pub enum Entity<'a> {
+    #[serde(rename = "light_spot")]
+    #[serde(borrow)]
+    LightSpot(LightSpot<'a>),
+    #[serde(rename = "func_door")]
+    #[serde(borrow)]
+    FuncDoor(FuncDoor<'a>),
+}
+
+#[doc = "A cone-shaped, invisible light source."]
+#[derive(Debug, Clone, ::serde::Deserialize)]
+pub struct LightSpot<'a> {
+    #[doc = "Name: The name that other entities refer to this entity by."]
+    #[serde(default)]
+    pub targetname: Option<&'a str>,
+    #[doc = "Pitch Yaw Roll (Y Z X)"]
+    #[serde(default)]
+    pub angles: Option<::vbsp::Angles>,
+    #[doc = "Brightness"]
+    #[serde(default)]
+    pub _light: Option<&'a str>,
+    #[doc = "Outer (fading) angle"]
+    #[serde(default)]
+    pub _cone: Option<i32>,
+    #[doc = "Focus"]
+    #[serde(default)]
+    pub _exponent: Option<f32>,
+    #[doc = "Appearance"]
+    #[serde(default)]
+    pub style: Option<i32>,
+    #[serde(default)]
+    pub spawnflags: Option<u32>,
+}
+
+#[allow(clippy::redundant_static_lifetimes)]
+impl LightSpot<'_> {
+    pub const CLASSNAME: &'static str = "light_spot";
+    pub const INPUTS: &'static [&'static str] = &["Kill"];
+    pub const OUTPUTS: &'static [&'static str] = &["OnUser1"];
+    pub const SPAWNFLAGS_INITIALLY_DARK: u32 = 1;
+}
+
+#[doc = "A door"]
+#[derive(Debug, Clone, ::serde::Deserialize)]
+pub struct FuncDoor<'a> {
+    #[doc = "Name: The name that other entities refer to this entity by."]
+    #[serde(default)]
+    pub targetname: Option<&'a str>,
+    #[doc = "Speed"]
+    #[serde(default)]
+    pub speed: Option<i32>,
+    #[doc = "Start Disabled"]
+    #[serde(default, deserialize_with = "deserialize_optional_bool")]
+    pub startdisabled: Option<bool>,
+    #[doc = "Render Color (R G B)"]
+    #[serde(default)]
+    pub rendercolor: Option<&'a str>,
+    #[doc = "Move Direction (Pitch Yaw Roll)"]
+    #[serde(default)]
+    pub movedir: Option<::vbsp::Angles>,
+    #[doc = "Type"]
+    #[serde(default)]
+    pub r#type: Option<i32>,
+}
+
+#[allow(clippy::redundant_static_lifetimes)]
+impl FuncDoor<'_> {
+    pub const CLASSNAME: &'static str = "func_door";
+    pub const INPUTS: &'static [&'static str] = &["Kill"];
+    pub const OUTPUTS: &'static [&'static str] = &["OnUser1", "OnOpen"];
+}
+
+#[allow(dead_code)]
+fn deserialize_optional_bool<'de, D: ::serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    ::vbsp::deserialize_bool(deserializer).map(Some)
+}
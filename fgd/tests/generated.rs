@@ -0,0 +1,69 @@
+//! Check that the generated code compiles and can be used to parse entities
+//!
+//! `data/entities.rs` is the output of `generate` for `data/test.fgd`, included the same way a build script output would be
+
+use vbsp::{Entities, LightColor};
+use vbsp_fgd::{generate, Fgd};
+
+#[allow(dead_code)]
+mod entities {
+    include!("data/entities.rs");
+}
+
+use entities::{Entity, FuncDoor, LightSpot};
+
+#[test]
+fn generated_code_is_up_to_date() {
+    let fgd = Fgd::parse(include_str!("data/test.fgd")).unwrap();
+    assert_eq!(include_str!("data/entities.rs"), generate(&fgd));
+}
+
+#[test]
+fn parse_generated_entities() {
+    let entities = Entities::new(
+        r#"
+{
+"classname" "light_spot"
+"targetname" "spot1"
+"angles" "-90 0 0"
+"_light" "255 200 100 400"
+"_cone" "60"
+"spawnflags" "1"
+}
+{
+"ClassName" "func_door"
+"speed" "200"
+"StartDisabled" "1"
+"rendercolor" "255 0 0"
+"type" "1"
+}
+"#
+        .into(),
+    );
+    let parsed: Vec<Entity> = entities
+        .iter()
+        .map(|entity| entity.parse().unwrap())
+        .collect();
+
+    let Entity::LightSpot(light) = &parsed[0] else {
+        panic!("expected a light_spot, got {:?}", parsed[0]);
+    };
+    assert_eq!(Some("spot1"), light.targetname);
+    assert_eq!(Some(-90.0), light.angles.map(|angles| angles.pitch));
+    assert_eq!(Some(60), light._cone);
+    assert_eq!(None, light._exponent);
+    assert_eq!(Some(LightSpot::SPAWNFLAGS_INITIALLY_DARK), light.spawnflags);
+    let color: LightColor = light._light.unwrap().parse().unwrap();
+    assert_eq!(400, color.intensity);
+
+    let Entity::FuncDoor(door) = &parsed[1] else {
+        panic!("expected a func_door, got {:?}", parsed[1]);
+    };
+    assert_eq!(Some(200), door.speed);
+    assert_eq!(Some(true), door.startdisabled);
+    assert_eq!(Some("255 0 0"), door.rendercolor);
+    assert_eq!(Some(1), door.r#type);
+    assert_eq!(None, door.targetname);
+    assert_eq!("func_door", FuncDoor::CLASSNAME);
+    assert!(FuncDoor::OUTPUTS.contains(&"OnOpen"));
+}
@@ -229,3 +229,23 @@ bitflags! {
         const SURFACE_PROP2 = 0x10;
     }
 }
+
+impl DisplacementTriangle {
+    /// Get which of the surface properties of the displacement's material the triangle uses
+    pub fn surface_prop(&self) -> DisplacementSurfaceProp {
+        if self.tags.contains(DisplacementTriangleFlags::SURFACE_PROP2) {
+            DisplacementSurfaceProp::Secondary
+        } else {
+            DisplacementSurfaceProp::Primary
+        }
+    }
+}
+
+/// The surface property used by a displacement triangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplacementSurfaceProp {
+    /// The `$surfaceprop` of the material
+    Primary,
+    /// The `$surfaceprop2` of a blended material
+    Secondary,
+}
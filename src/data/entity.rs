@@ -1,15 +1,27 @@
 use crate::error::EntityParseError;
-use crate::EntityProp;
-use serde::Deserialize;
-use std::collections::HashMap;
+use crate::{Angles, EntityConnection, EntityProp, Vector};
+use cgmath::{Matrix4, Quaternion, Rotation};
+use serde::de::{Error as _, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 use std::fmt;
 use std::fmt::Debug;
-use vdf_reader::entry::Entry;
+use std::ops::Range;
+use thiserror::Error;
 use vdf_reader::VdfError;
 
 #[derive(Clone)]
 pub struct Entities {
-    pub entities: String,
+    entities: String,
+    /// The entity text with all keys and classnames in lowercase, used for deserializing entities
+    ///
+    /// Since only ascii characters are lowercased, offsets into this are the same as in `entities`
+    normalized: String,
+}
+
+impl From<String> for Entities {
+    fn from(entities: String) -> Self {
+        Entities::new(entities)
+    }
 }
 
 impl fmt::Debug for Entities {
@@ -27,20 +39,16 @@ impl fmt::Debug for Entities {
     }
 }
 pub struct EntitiesIter<'a> {
-    buf: &'a str,
+    entities: &'a Entities,
+    tokens: Tokenizer<'a>,
 }
 
 impl<'a> Iterator for EntitiesIter<'a> {
     type Item = RawEntity<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let start = self.buf.find('{')?;
-        let slice = &self.buf[start..];
-        let end = slice.find('}')?;
-        let (out, rest) = slice.split_at(end + 1);
-
-        self.buf = rest;
-        Some(RawEntity { buf: out })
+        let range = next_entity(&mut self.tokens).ok()??;
+        Some(self.entities.entity(range))
     }
 }
 
@@ -55,23 +63,297 @@ impl<'a> IntoIterator for &'a Entities {
 }
 
 impl Entities {
+    pub fn new(entities: String) -> Self {
+        let normalized = normalize_entities(&entities);
+        Entities {
+            entities,
+            normalized,
+        }
+    }
+
+    /// Get the raw text of the entity lump
+    pub fn as_str(&self) -> &str {
+        &self.entities
+    }
+
+    /// Iterate over all entities
+    ///
+    /// Iteration stops at the first malformed entity, use [`Entities::try_iter`] to get the syntax errors
     pub fn iter(&self) -> EntitiesIter {
         EntitiesIter {
-            buf: &self.entities,
+            entities: self,
+            tokens: Tokenizer::new(&self.entities),
+        }
+    }
+
+    /// Iterate over all entities, returning an error for the first malformed entity
+    pub fn try_iter(&self) -> impl Iterator<Item = Result<RawEntity<'_>, EntitySyntaxError>> {
+        let mut tokens = Tokenizer::new(&self.entities);
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            match next_entity(&mut tokens) {
+                Ok(range) => range.map(|range| Ok(self.entity(range))),
+                Err(e) => {
+                    failed = true;
+                    Some(Err(e))
+                }
+            }
+        })
+    }
+
+    fn entity(&self, range: Range<usize>) -> RawEntity<'_> {
+        RawEntity {
+            buf: &self.entities[range.clone()],
+            normalized: &self.normalized[range],
+        }
+    }
+}
+
+/// Lowercase all keys and classnames in the entity text
+///
+/// Entity keys and classnames are case-insensitive, but deserializing them requires matching them to lowercase field names
+fn normalize_entities(entities: &str) -> String {
+    let mut normalized = entities.to_string();
+    let mut tokens = Tokenizer::new(entities);
+    let mut key: Option<&str> = None;
+
+    while let Ok(Some(token)) = tokens.next_token() {
+        match (token.kind, key) {
+            (TokenKind::String(value), None) => {
+                normalized[token.content.clone()].make_ascii_lowercase();
+                key = Some(value);
+            }
+            (TokenKind::String(_), Some(key_value)) => {
+                if key_value.eq_ignore_ascii_case("classname") {
+                    normalized[token.content.clone()].make_ascii_lowercase();
+                }
+                key = None;
+            }
+            _ => key = None,
+        }
+    }
+    normalized
+}
+
+/// Find the next entity in the token stream, returning the range of the entity text including braces
+fn next_entity(tokens: &mut Tokenizer) -> Result<Option<Range<usize>>, EntitySyntaxError> {
+    let Some(open) = tokens.next_token()? else {
+        return Ok(None);
+    };
+    if open.kind != TokenKind::Open {
+        return Err(open.unexpected("'{'"));
+    }
+
+    loop {
+        let token = tokens.expect("a key or '}'")?;
+        match token.kind {
+            TokenKind::Close => return Ok(Some(open.span.start..token.span.end)),
+            TokenKind::String(_) => {
+                let value = tokens.expect("a value")?;
+                if !matches!(value.kind, TokenKind::String(_)) {
+                    return Err(value.unexpected("a value"));
+                }
+            }
+            TokenKind::Open => return Err(token.unexpected("a key or '}'")),
+        }
+    }
+}
+
+/// A syntax error in the entity lump
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{kind} at line {line}, column {column}")]
+pub struct EntitySyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: EntitySyntaxErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EntitySyntaxErrorKind {
+    #[error("unterminated string")]
+    UnterminatedString,
+    #[error("unexpected end of input, expected {expected}")]
+    UnexpectedEnd { expected: &'static str },
+    #[error("unexpected {found}, expected {expected}")]
+    UnexpectedToken {
+        expected: &'static str,
+        found: String,
+    },
+}
+
+/// An entity property that can't be written to the entity lump
+///
+/// The entity lump has no escape sequences, so keys and values can't contain `"`, newlines or null bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("property {key:?} of entity {entity} contains characters that can't be stored in the entity lump")]
+pub struct InvalidEntityProperty {
+    /// Index of the entity in the list
+    pub entity: usize,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind<'a> {
+    Open,
+    Close,
+    String(&'a str),
+}
+
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    kind: TokenKind<'a>,
+    /// Range of the token in the text, including quotes
+    span: Range<usize>,
+    /// Range of the token contents in the text, excluding quotes
+    content: Range<usize>,
+    line: usize,
+    column: usize,
+}
+
+impl Token<'_> {
+    fn unexpected(&self, expected: &'static str) -> EntitySyntaxError {
+        let found = match self.kind {
+            TokenKind::Open => "'{'".into(),
+            TokenKind::Close => "'}'".into(),
+            TokenKind::String(value) => format!("\"{value}\""),
+        };
+        EntitySyntaxError {
+            line: self.line,
+            column: self.column,
+            kind: EntitySyntaxErrorKind::UnexpectedToken { expected, found },
+        }
+    }
+}
+
+/// Tokenizer for the keyvalues text of the entity lump
+#[derive(Clone)]
+struct Tokenizer<'a> {
+    text: &'a str,
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(text: &'a str) -> Self {
+        Tokenizer {
+            text,
+            position: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                // the entity lump is null terminated
+                Some(c) if c.is_whitespace() || c == '\0' => {
+                    self.bump();
+                }
+                Some('/') if self.text[self.position..].starts_with("//") => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
         }
     }
+
+    fn expect(&mut self, expected: &'static str) -> Result<Token<'a>, EntitySyntaxError> {
+        self.next_token()?.ok_or(EntitySyntaxError {
+            line: self.line,
+            column: self.column,
+            kind: EntitySyntaxErrorKind::UnexpectedEnd { expected },
+        })
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, EntitySyntaxError> {
+        self.skip_whitespace();
+        let (start, line, column) = (self.position, self.line, self.column);
+        let token = |kind, span: Range<usize>, content| Token {
+            kind,
+            span,
+            content,
+            line,
+            column,
+        };
+
+        let Some(c) = self.bump() else {
+            return Ok(None);
+        };
+        Ok(Some(match c {
+            '{' => token(TokenKind::Open, start..self.position, start..self.position),
+            '}' => token(TokenKind::Close, start..self.position, start..self.position),
+            '"' => {
+                let content_start = self.position;
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some(_) => {}
+                        None => {
+                            return Err(EntitySyntaxError {
+                                line,
+                                column,
+                                kind: EntitySyntaxErrorKind::UnterminatedString,
+                            });
+                        }
+                    }
+                }
+                let content = content_start..self.position - 1;
+                token(
+                    TokenKind::String(&self.text[content.clone()]),
+                    start..self.position,
+                    content,
+                )
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && !matches!(c, '{' | '}' | '"' | '\0'))
+                {
+                    self.bump();
+                }
+                let content = start..self.position;
+                token(
+                    TokenKind::String(&self.text[content.clone()]),
+                    content.clone(),
+                    content,
+                )
+            }
+        }))
+    }
 }
 
 #[derive(Clone)]
 pub struct RawEntity<'a> {
     buf: &'a str,
+    normalized: &'a str,
 }
 
 impl fmt::Debug for RawEntity<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use std::collections::HashMap;
-
-        self.properties().collect::<HashMap<_, _>>().fmt(f)
+        f.debug_map().entries(self.properties()).finish()
     }
 }
 
@@ -80,57 +362,665 @@ impl<'a> RawEntity<'a> {
         self.buf
     }
 
-    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
-        struct Iter<'a> {
-            buf: &'a str,
+    /// Iterate over all properties of the entity in order, including duplicate keys
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        let mut tokens = Tokenizer::new(self.buf);
+        // skip the opening brace
+        let _ = tokens.next_token();
+        std::iter::from_fn(move || {
+            let key = tokens.next_token().ok()??;
+            let value = tokens.next_token().ok()??;
+            match (key.kind, value.kind) {
+                (TokenKind::String(key), TokenKind::String(value)) => Some((key, value)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Get the value of a property, keys are matched case-insensitively
+    ///
+    /// If the key occurs multiple times, the first value is returned
+    pub fn prop(&self, key: &str) -> Option<&'a str> {
+        self.props_all(key).next()
+    }
+
+    /// Get all values of a property in order, keys are matched case-insensitively
+    pub fn props_all<'b>(&self, key: &'b str) -> impl Iterator<Item = &'a str> + use<'a, 'b> {
+        self.properties().filter_map(move |(prop_key, value)| {
+            key.eq_ignore_ascii_case(prop_key).then_some(value)
+        })
+    }
+
+    pub fn prop_parse<T: EntityProp<'a>>(&self, key: &str) -> Option<Result<T, EntityParseError>> {
+        self.prop(key).map(T::parse)
+    }
+
+    /// Iterate over all outputs of the entity as `(output, connection)`
+    ///
+    /// Any property whose value is a valid connection string is treated as an output
+    pub fn connections(&self) -> impl Iterator<Item = (&'a str, EntityConnection)> + use<'a> {
+        self.properties().filter_map(|(key, value)| {
+            let connection = value.parse().ok()?;
+            Some((key, connection))
+        })
+    }
+
+    /// Get the index of the brush model used by the entity, for entities with a `"model" "*N"` property
+    pub fn brush_model_index(&self) -> Option<usize> {
+        self.prop("model")?.strip_prefix('*')?.parse().ok()
+    }
+
+    /// Get the position and rotation of the entity from its `origin` and `angles`
+    ///
+    /// Missing or malformed properties are treated as zero.
+    pub fn transform(&self) -> EntityTransform {
+        EntityTransform {
+            origin: self
+                .prop_parse("origin")
+                .and_then(Result::ok)
+                .unwrap_or_default(),
+            angles: self
+                .prop_parse("angles")
+                .and_then(Result::ok)
+                .unwrap_or_default(),
         }
+    }
+
+    /// Deserialize the entity
+    ///
+    /// Keys and the classname are lowercased before deserializing, other values keep their original case
+    pub fn parse<E: Deserialize<'a>>(&self) -> Result<E, VdfError> {
+        vdf_reader::from_str(self.normalized)
+    }
+}
+
+/// The placement of an entity in the world
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EntityTransform {
+    pub origin: Vector,
+    pub angles: Angles,
+}
+
+impl EntityTransform {
+    /// The rotation in map coordinates, with z up
+    ///
+    /// See [`Angles::rotation`], use [`Angles::as_quaternion`] for the rotation in y-up coordinates
+    pub fn rotation(&self) -> Quaternion<f32> {
+        self.angles.rotation()
+    }
+
+    /// Transform a point from entity space into world space
+    pub fn apply(&self, point: Vector) -> Vector {
+        let rotated = self.rotation().rotate_vector(point.into());
+        Vector::from([rotated.x, rotated.y, rotated.z]) + self.origin
+    }
+
+    /// The transform as a column-major matrix
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.origin.into()) * Matrix4::from(self.rotation())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GenericEntity {
+    pub class: String,
+    /// All properties except the classname, in order
+    pub data: EntityProperties,
+}
+
+impl<'de> Deserialize<'de> for GenericEntity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut data = EntityProperties::deserialize(deserializer)?;
+        let class = data
+            .remove("classname")
+            .ok_or_else(|| D::Error::missing_field("classname"))?;
+        Ok(GenericEntity { class, data })
+    }
+}
 
-        impl<'a> Iterator for Iter<'a> {
-            type Item = (&'a str, &'a str);
+/// Ordered key-value pairs of an entity, keys can occur multiple times
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntityProperties {
+    entries: Vec<(String, String)>,
+}
 
-            fn next(&mut self) -> Option<Self::Item> {
-                let start = self.buf.find('"')? + 1;
-                let end = start + self.buf[start..].find('"')?;
+impl EntityProperties {
+    pub fn new() -> Self {
+        EntityProperties::default()
+    }
 
-                let key = &self.buf[start..end];
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
 
-                let rest = &self.buf[end + 1..];
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
 
-                let start = rest.find('"')? + 1;
-                let end = start + rest[start..].find('"')?;
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
 
-                let value = &rest[start..end];
+    /// Get the first value for a key, keys are matched case-insensitively
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).next()
+    }
 
-                self.buf = &rest[end + 1..];
+    /// Get all values for a key in order, keys are matched case-insensitively
+    pub fn get_all<'a, 'b>(&'a self, key: &'b str) -> impl Iterator<Item = &'a str> + use<'a, 'b> {
+        self.iter().filter_map(move |(prop_key, value)| {
+            key.eq_ignore_ascii_case(prop_key).then_some(value)
+        })
+    }
 
-                Some((key, value))
+    /// Add a value to the end of the properties, keeping any existing values for the key
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((key.into(), value.into()));
+    }
+
+    /// Remove all values for a key, returning the first removed value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let mut removed = None;
+        self.entries.retain_mut(|(prop_key, value)| {
+            if prop_key.eq_ignore_ascii_case(key) {
+                if removed.is_none() {
+                    removed = Some(std::mem::take(value));
+                }
+                false
+            } else {
+                true
             }
+        });
+        removed
+    }
+}
+
+impl EntityProperties {
+    /// Set the value for a key, replacing the first existing value and removing any duplicates
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = value.into();
+        match self
+            .entries
+            .iter()
+            .position(|(prop_key, _)| prop_key.eq_ignore_ascii_case(&key))
+        {
+            Some(index) => {
+                self.entries[index].1 = value;
+                let mut position = 0;
+                self.entries.retain(|(prop_key, _)| {
+                    position += 1;
+                    position - 1 <= index || !prop_key.eq_ignore_ascii_case(&key)
+                });
+            }
+            None => self.entries.push((key, value)),
         }
+    }
 
-        Iter { buf: self.buf }
+    pub fn class(&self) -> Option<&str> {
+        self.get("classname")
     }
 
-    pub fn prop(&self, key: &'static str) -> Option<&'a str> {
-        self.properties()
-            .find_map(|(prop_key, value)| (key == prop_key).then_some(value))
+    /// Find the first property that can't be written to the entity lump
+    fn invalid_key(&self) -> Option<&str> {
+        let invalid = |text: &str| text.contains(['"', '\n', '\r', '\0']);
+        self.iter()
+            .find(|(key, value)| invalid(key) || invalid(value))
+            .map(|(key, _)| key)
     }
+}
 
-    pub fn prop_parse<T: EntityProp<'a>>(
-        &self,
-        key: &'static str,
-    ) -> Option<Result<T, EntityParseError>> {
-        self.prop(key).map(T::parse)
+impl From<&RawEntity<'_>> for EntityProperties {
+    fn from(entity: &RawEntity<'_>) -> Self {
+        entity.properties().collect()
     }
+}
 
-    pub fn parse<E: Deserialize<'a>>(&self) -> Result<E, VdfError> {
-        vdf_reader::from_str(self.buf)
+/// Writes the properties as a `{ "key" "value" }` block in the format used by the entity lump
+///
+/// The format has no escape sequences, so keys and values containing `"` or newlines are written
+/// as-is, use [`EntityList::to_lump_string`] to reject those.
+impl fmt::Display for EntityProperties {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{{")?;
+        for (key, value) in self.iter() {
+            writeln!(f, "\"{key}\" \"{value}\"")?;
+        }
+        writeln!(f, "}}")
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
-pub struct GenericEntity {
-    #[serde(rename = "classname")]
-    pub class: String,
-    #[serde(flatten)]
-    pub data: HashMap<String, Entry>,
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for EntityProperties {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        EntityProperties {
+            entries: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for EntityProperties {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.entries.extend(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+    }
+}
+
+impl IntoIterator for EntityProperties {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityProperties {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PropertiesVisitor;
+
+        impl<'de> Visitor<'de> for PropertiesVisitor {
+            type Value = EntityProperties;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("entity properties")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut properties = EntityProperties::new();
+                while let Some((key, value)) = map.next_entry::<String, String>()? {
+                    properties.push(key, value);
+                }
+                Ok(properties)
+            }
+        }
+
+        deserializer.deserialize_map(PropertiesVisitor)
+    }
+}
+
+/// An owned, editable list of entities
+///
+/// Entities keep the order and case of their properties, so unmodified entities serialize
+/// to the same properties they were read from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntityList {
+    entities: Vec<EntityProperties>,
+}
+
+impl EntityList {
+    pub fn new() -> Self {
+        EntityList::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EntityProperties> {
+        self.entities.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut EntityProperties> {
+        self.entities.iter_mut()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&EntityProperties> {
+        self.entities.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut EntityProperties> {
+        self.entities.get_mut(index)
+    }
+
+    /// Add an entity to the end of the list
+    pub fn push(&mut self, entity: EntityProperties) {
+        self.entities.push(entity);
+    }
+
+    /// Insert an entity at `index`, shifting all entities after it
+    ///
+    /// Note that the engine expects `worldspawn` to be the first entity.
+    pub fn insert(&mut self, index: usize, entity: EntityProperties) {
+        self.entities.insert(index, entity);
+    }
+
+    pub fn remove(&mut self, index: usize) -> EntityProperties {
+        self.entities.remove(index)
+    }
+
+    /// Keep only the entities for which `keep` returns true
+    pub fn retain(&mut self, keep: impl FnMut(&EntityProperties) -> bool) {
+        self.entities.retain(keep);
+    }
+
+    /// Iterate over all entities with a classname, matched case-insensitively
+    pub fn by_class<'a, 'b>(
+        &'a self,
+        class: &'b str,
+    ) -> impl Iterator<Item = &'a EntityProperties> + use<'a, 'b> {
+        self.iter().filter(move |entity| {
+            entity
+                .class()
+                .is_some_and(|entity_class| entity_class.eq_ignore_ascii_case(class))
+        })
+    }
+
+    /// Iterate over all entities with a targetname, matched case-insensitively
+    pub fn by_name<'a, 'b>(
+        &'a self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'a EntityProperties> + use<'a, 'b> {
+        self.iter().filter(move |entity| {
+            entity
+                .get("targetname")
+                .is_some_and(|entity_name| entity_name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Serialize the entities into the text of an entity lump
+    ///
+    /// Fails if any key or value contains characters that can't be stored in the entity lump.
+    pub fn to_lump_string(&self) -> Result<String, InvalidEntityProperty> {
+        for (index, entity) in self.entities.iter().enumerate() {
+            if let Some(key) = entity.invalid_key() {
+                return Err(InvalidEntityProperty {
+                    entity: index,
+                    key: key.into(),
+                });
+            }
+        }
+        Ok(self.to_string())
+    }
+
+    /// Serialize the entities into the contents of an entity lump, including the trailing null byte
+    pub fn to_lump(&self) -> Result<Vec<u8>, InvalidEntityProperty> {
+        let mut data = self.to_lump_string()?.into_bytes();
+        data.push(0);
+        Ok(data)
+    }
+}
+
+impl fmt::Display for EntityList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entity in &self.entities {
+            write!(f, "{entity}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&Entities> for EntityList {
+    type Error = EntitySyntaxError;
+
+    fn try_from(entities: &Entities) -> Result<Self, Self::Error> {
+        entities
+            .try_iter()
+            .map(|entity| entity.map(|entity| (&entity).into()))
+            .collect()
+    }
+}
+
+impl TryFrom<&EntityList> for Entities {
+    type Error = InvalidEntityProperty;
+
+    fn try_from(list: &EntityList) -> Result<Self, Self::Error> {
+        Ok(Entities::new(list.to_lump_string()?))
+    }
+}
+
+impl FromIterator<EntityProperties> for EntityList {
+    fn from_iter<T: IntoIterator<Item = EntityProperties>>(iter: T) -> Self {
+        EntityList {
+            entities: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for EntityList {
+    type Item = EntityProperties;
+    type IntoIter = std::vec::IntoIter<EntityProperties>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.into_iter()
+    }
+}
+
+#[test]
+fn test_entity_case() {
+    let entities = Entities::from(String::from(
+        r#"{
+"ClassName" "Game_Text"
+"targetname" "Intro_Text"
+"Message" "Hello World"
+}
+{
+"classname" "info_target"
+}"#,
+    ));
+    let entity = entities.iter().next().unwrap();
+    assert_eq!(Some("Game_Text"), entity.prop("classname"));
+    assert_eq!(Some("Hello World"), entity.prop("message"));
+    assert_eq!(Some("Intro_Text"), entity.prop("TARGETNAME"));
+
+    let generic: GenericEntity = entity.parse().unwrap();
+    assert_eq!("game_text", generic.class);
+    assert_eq!(Some("Hello World"), generic.data.get("message"));
+    assert_eq!(Some("Intro_Text"), generic.data.get("targetname"));
+
+    let second = entities.iter().nth(1).unwrap();
+    assert_eq!(Some("info_target"), second.prop("classname"));
+}
+
+#[test]
+fn test_entity_tokenizer() {
+    let entities = Entities::from(String::from(
+        "{\n\"classname\" \"game_text\"\n\"message\" \"{ braces } in text\"\n\"OnUser1\" \"a,b\"\n\"OnUser1\" \"c,d\"\n}\n{\nclassname info_target\n}\n\0",
+    ));
+    let entities: Vec<_> = entities.try_iter().collect::<Result<_, _>>().unwrap();
+    assert_eq!(2, entities.len());
+    assert_eq!(Some("{ braces } in text"), entities[0].prop("message"));
+    assert_eq!(
+        vec![
+            ("classname", "game_text"),
+            ("message", "{ braces } in text"),
+            ("OnUser1", "a,b"),
+            ("OnUser1", "c,d")
+        ],
+        entities[0].properties().collect::<Vec<_>>()
+    );
+    assert_eq!(Some("info_target"), entities[1].prop("classname"));
+
+    let generic: GenericEntity = entities[0].parse().unwrap();
+    assert_eq!(Some("{ braces } in text"), generic.data.get("message"));
+}
+
+#[test]
+fn test_entity_syntax_error() {
+    let entities = Entities::from(String::from(
+        "{\n\"classname\" \"info_target\"\n}\n{\n\"classname\" \"info_target\"\n\"origin\"\n}",
+    ));
+    let result: Vec<_> = entities.try_iter().collect();
+    assert_eq!(2, result.len());
+    assert!(result[0].is_ok());
+    assert_eq!(
+        &EntitySyntaxError {
+            line: 7,
+            column: 1,
+            kind: EntitySyntaxErrorKind::UnexpectedToken {
+                expected: "a value",
+                found: "'}'".into()
+            }
+        },
+        result[1].as_ref().unwrap_err()
+    );
+    assert_eq!(1, entities.iter().count());
+
+    let unterminated = Entities::from(String::from("{\n  \"classname\" \"info_target\n}"));
+    assert_eq!(
+        EntitySyntaxErrorKind::UnterminatedString,
+        unterminated.try_iter().next().unwrap().unwrap_err().kind
+    );
+}
+
+#[test]
+fn test_duplicate_properties() {
+    let entities = Entities::from(String::from(
+        r#"{
+"classname" "trigger_multiple"
+"OnTrigger" "door1,Open,,0,-1"
+"wait" "1.50"
+"ontrigger" "door2,Open,,0,-1"
+}"#,
+    ));
+    let entity = entities.iter().next().unwrap();
+    assert_eq!(
+        vec!["door1,Open,,0,-1", "door2,Open,,0,-1"],
+        entity.props_all("OnTrigger").collect::<Vec<_>>()
+    );
+
+    let generic: GenericEntity = entity.parse().unwrap();
+    assert_eq!("trigger_multiple", generic.class);
+    assert_eq!(
+        vec![
+            ("ontrigger", "door1,Open,,0,-1"),
+            ("wait", "1.50"),
+            ("ontrigger", "door2,Open,,0,-1")
+        ],
+        generic.data.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(2, generic.data.get_all("OnTrigger").count());
+}
+
+#[test]
+fn test_entity_list() {
+    let entities = Entities::new(
+        r#"
+{
+"classname" "worldspawn"
+"mapversion" "12"
+}
+{
+"classname" "tf_logic_koth"
+"timer_length" "180"
+}
+{
+"classname" "info_player_teamspawn"
+"TeamNum" "2"
+"OnUser1" "!self,Kill,,0,-1"
+"OnUser1" "!self,Enable,,1,-1"
+}
+"#
+        .into(),
+    );
+    let mut list = EntityList::try_from(&entities).unwrap();
+    assert_eq!(3, list.len());
+    list.retain(|entity| {
+        !entity
+            .class()
+            .is_some_and(|class| class.starts_with("tf_logic_"))
+    });
+    list.get_mut(1).unwrap().set("teamnum", "3");
+    list.push(EntityProperties::from_iter([
+        ("classname", "info_target"),
+        ("targetname", "target1"),
+    ]));
+
+    assert_eq!(
+        "{\n\"classname\" \"worldspawn\"\n\"mapversion\" \"12\"\n}\n\
+        {\n\"classname\" \"info_player_teamspawn\"\n\"TeamNum\" \"3\"\n\
+        \"OnUser1\" \"!self,Kill,,0,-1\"\n\"OnUser1\" \"!self,Enable,,1,-1\"\n}\n\
+        {\n\"classname\" \"info_target\"\n\"targetname\" \"target1\"\n}\n",
+        list.to_string()
+    );
+    assert_eq!(Some(&0), list.to_lump().unwrap().last());
+    assert_eq!(1, list.by_name("TARGET1").count());
+
+    let reparsed = Entities::try_from(&list).unwrap();
+    assert_eq!(list, EntityList::try_from(&reparsed).unwrap());
+
+    list.get_mut(2).unwrap().set("message", "say \"hi\"");
+    assert_eq!(
+        Err(InvalidEntityProperty {
+            entity: 2,
+            key: "message".into()
+        }),
+        list.to_lump()
+    );
+    list.get_mut(2).unwrap().set("message", "line\nbreak");
+    assert!(list.to_lump_string().is_err());
+
+    let truncated = Entities::new("{\n\"classname\" \"worldspawn\"\n".into());
+    assert!(EntityList::try_from(&truncated).is_err());
+}
+
+#[test]
+fn test_set_property() {
+    let mut properties = EntityProperties::from_iter([("a", "1"), ("b", "2"), ("A", "3")]);
+    properties.set("A", "4");
+    assert_eq!(
+        vec![("a", "4"), ("b", "2")],
+        properties.iter().collect::<Vec<_>>()
+    );
+    properties.set("c", "5");
+    assert_eq!(Some("5"), properties.get("C"));
+}
+
+#[test]
+fn test_entity_transform() {
+    let entities = Entities::new(
+        r#"
+{
+"classname" "func_door"
+"model" "*12"
+"origin" "100 0 -50"
+"angles" "0 90 0"
+}
+{
+"classname" "prop_static"
+"model" "models/props/barrel.mdl"
+}
+"#
+        .into(),
+    );
+    let mut iter = entities.iter();
+    let door = iter.next().unwrap();
+    let prop = iter.next().unwrap();
+    assert_eq!(Some(12), door.brush_model_index());
+    assert_eq!(None, prop.brush_model_index());
+
+    let transform = door.transform();
+    let point = transform.apply(Vector::from([10.0, 0.0, 5.0]));
+    assert!((point - Vector::from([100.0, 10.0, -45.0])).length_squared() < 0.0001);
+
+    let pitched = EntityTransform {
+        origin: Vector::default(),
+        angles: "90 0 0".parse().unwrap(),
+    };
+    // positive pitch looks down
+    let forward = pitched.apply(Vector::from([1.0, 0.0, 0.0]));
+    assert!((forward - Vector::from([0.0, 0.0, -1.0])).length_squared() < 0.0001);
+
+    assert_eq!(Vector::default(), prop.transform().origin);
+
+    // both rotations describe the same orientation, in differently oriented coordinates
+    let tilted = Entities::new(r#"{ "classname" "prop_dynamic" "angles" "-30 45 10" }"#.into());
+    let transform = tilted.iter().next().unwrap().transform();
+    let point = Vector::from([1.0, 2.0, 3.0]);
+    let map = transform.apply(point);
+    let y_up = transform
+        .angles
+        .as_quaternion()
+        .rotate_vector([point.y, point.z, point.x].into());
+    assert!((Vector::from([y_up.z, y_up.x, y_up.y]) - map).length_squared() < 0.0001);
 }
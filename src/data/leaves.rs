@@ -1,4 +1,5 @@
 use std::io::{Read, Seek};
+use std::iter::Peekable;
 use std::mem::{align_of, size_of};
 use std::ops::Deref;
 
@@ -15,9 +16,7 @@ pub struct Leaves {
 }
 
 impl Leaves {
-    pub fn new(mut leaves: Vec<Leaf>) -> Self {
-        leaves.sort_unstable_by_key(|leaf| leaf.cluster);
-
+    pub fn new(leaves: Vec<Leaf>) -> Self {
         Leaves { leaves }
     }
 
@@ -33,10 +32,12 @@ impl Leaves {
         self.leaves
     }
 
+    /// Get the leaves grouped by cluster, ordered by cluster index
     pub fn clusters(&self) -> impl Iterator<Item = impl Iterator<Item = &Leaf>> {
+        let mut leaves: Vec<&Leaf> = self.leaves.iter().collect();
+        leaves.sort_by_key(|leaf| leaf.cluster);
         LeafClusters {
-            leaves: &self.leaves,
-            index: 0,
+            leaves: leaves.into_iter().peekable(),
         }
     }
 }
@@ -92,22 +93,19 @@ impl BinRead for Leaves {
 }
 
 struct LeafClusters<'a> {
-    leaves: &'a [Leaf],
-    index: usize,
+    leaves: Peekable<std::vec::IntoIter<&'a Leaf>>,
 }
 
 impl<'a> Iterator for LeafClusters<'a> {
-    type Item = <&'a [Leaf] as IntoIterator>::IntoIter;
+    type Item = std::vec::IntoIter<&'a Leaf>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let cluster = self.leaves.get(self.index)?.cluster;
-        let remaining_leaves = self.leaves.get(self.index..)?;
-        let cluster_size = remaining_leaves
-            .iter()
-            .take_while(|leaf| leaf.cluster == cluster)
-            .count();
-        self.index += cluster_size;
-        Some(remaining_leaves[0..cluster_size].iter())
+        let first = self.leaves.next()?;
+        let mut cluster = vec![first];
+        while let Some(leaf) = self.leaves.next_if(|leaf| leaf.cluster == first.cluster) {
+            cluster.push(leaf);
+        }
+        Some(cluster.into_iter())
     }
 }
 
@@ -125,13 +123,13 @@ fn test_leaf_clusters() {
             ..Default::default()
         },
         Leaf {
-            contents: 2,
-            cluster: 1,
+            contents: 3,
+            cluster: 2,
             ..Default::default()
         },
         Leaf {
-            contents: 3,
-            cluster: 2,
+            contents: 2,
+            cluster: 1,
             ..Default::default()
         },
         Leaf {
@@ -142,6 +140,12 @@ fn test_leaf_clusters() {
     ]
     .into();
 
+    // leaves keep the order from the bsp file
+    assert_eq!(
+        vec![0, 1, 3, 2, 4],
+        leaves.iter().map(|leaf| leaf.contents).collect::<Vec<_>>()
+    );
+
     let clustered: Vec<Vec<i32>> = leaves
         .clusters()
         .map(|cluster| cluster.map(|leaf| leaf.contents).collect())
@@ -325,3 +329,18 @@ impl BinRead for Leaf {
         }
     }
 }
+
+#[derive(Default, Debug, Clone, BinRead)]
+pub struct LeafWaterData {
+    pub surface_z: f32,
+    pub min_z: f32,
+    #[br(align_after = align_of::<LeafWaterData>())]
+    pub surface_texture_info: i16,
+}
+
+static_assertions::const_assert_eq!(size_of::<LeafWaterData>(), 12);
+
+#[test]
+fn test_leaf_water_data_bytes() {
+    super::test_read_bytes::<LeafWaterData>();
+}
@@ -0,0 +1,249 @@
+use crate::{Entities, EntityConnection};
+use std::collections::HashMap;
+
+/// The io connections between the entities in a map
+///
+/// Entities are identified by their index in [`Entities::iter`].
+#[derive(Debug, Clone)]
+pub struct EntityGraph {
+    entities: Vec<EntityNode>,
+    connections: Vec<GraphConnection>,
+    /// Entity indices by lowercase targetname
+    names: HashMap<String, Vec<usize>>,
+    /// Entity indices by lowercase classname
+    classes: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntityNode {
+    pub class: String,
+    pub targetname: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphConnection {
+    /// Index of the entity firing the output
+    pub source: usize,
+    pub output: String,
+    pub connection: EntityConnection,
+    pub target: ConnectionTarget,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionTarget {
+    /// The entities matching the target by targetname, or by classname if no targetname matches
+    Entities(Vec<usize>),
+    /// A target that is only known when the output fires
+    Special(SpecialTarget),
+    /// No entity in the map matches the target
+    Unresolved,
+}
+
+/// Special `!name` targets resolved by the engine at runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecialTarget {
+    /// `!activator`, the entity that started the chain of outputs
+    Activator,
+    /// `!caller`, the entity that fired the output
+    Caller,
+    /// `!player`, the first player
+    Player,
+    /// `!picker`, the entity the player is looking at
+    Picker,
+    Other(String),
+}
+
+impl SpecialTarget {
+    /// Parse a `!name` target, `!self` isn't included since it refers to the source entity
+    fn parse(target: &str) -> Option<Self> {
+        let name = target.strip_prefix('!')?.to_ascii_lowercase();
+        Some(match name.as_str() {
+            "activator" => SpecialTarget::Activator,
+            "caller" => SpecialTarget::Caller,
+            "player" => SpecialTarget::Player,
+            "picker" => SpecialTarget::Picker,
+            _ => SpecialTarget::Other(name),
+        })
+    }
+}
+
+impl EntityGraph {
+    pub fn new(entities: &Entities) -> Self {
+        let mut nodes = Vec::new();
+        let mut outputs = Vec::new();
+        let mut names: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut classes: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, entity) in entities.iter().enumerate() {
+            let class = entity.prop("classname").unwrap_or_default().to_string();
+            let targetname = entity.prop("targetname").map(String::from);
+            classes
+                .entry(class.to_ascii_lowercase())
+                .or_default()
+                .push(index);
+            if let Some(name) = &targetname {
+                names
+                    .entry(name.to_ascii_lowercase())
+                    .or_default()
+                    .push(index);
+            }
+            outputs.extend(
+                entity
+                    .connections()
+                    .map(|(output, connection)| (index, output.to_string(), connection)),
+            );
+            nodes.push(EntityNode { class, targetname });
+        }
+
+        let mut graph = EntityGraph {
+            entities: nodes,
+            connections: Vec::with_capacity(outputs.len()),
+            names,
+            classes,
+        };
+        graph.connections = outputs
+            .into_iter()
+            .map(|(source, output, connection)| GraphConnection {
+                target: graph.resolve(&connection.target, source),
+                source,
+                output,
+                connection,
+            })
+            .collect();
+        graph
+    }
+
+    pub fn entities(&self) -> &[EntityNode] {
+        &self.entities
+    }
+
+    pub fn entity(&self, index: usize) -> Option<&EntityNode> {
+        self.entities.get(index)
+    }
+
+    pub fn connections(&self) -> &[GraphConnection] {
+        &self.connections
+    }
+
+    /// Get the connections fired by an entity
+    pub fn outgoing(&self, index: usize) -> impl Iterator<Item = &GraphConnection> {
+        self.connections
+            .iter()
+            .filter(move |connection| connection.source == index)
+    }
+
+    /// Get the connections that target an entity
+    pub fn incoming(&self, index: usize) -> impl Iterator<Item = &GraphConnection> {
+        self.connections.iter().filter(move |connection| {
+            matches!(&connection.target, ConnectionTarget::Entities(targets) if targets.contains(&index))
+        })
+    }
+
+    /// Get the connections whose target doesn't match any entity in the map
+    pub fn unresolved(&self) -> impl Iterator<Item = &GraphConnection> {
+        self.connections
+            .iter()
+            .filter(|connection| connection.target == ConnectionTarget::Unresolved)
+    }
+
+    /// Find the entities matching a target name, `source` is the entity referred to by `!self`
+    ///
+    /// Targets are matched case-insensitively against targetnames and fall back to classnames,
+    /// a trailing `*` matches any suffix.
+    pub fn resolve(&self, target: &str, source: usize) -> ConnectionTarget {
+        if target.eq_ignore_ascii_case("!self") {
+            return ConnectionTarget::Entities(vec![source]);
+        }
+        if let Some(special) = SpecialTarget::parse(target) {
+            return ConnectionTarget::Special(special);
+        }
+
+        let target = target.to_ascii_lowercase();
+        let found = find(&self.names, &target);
+        let found = if found.is_empty() {
+            find(&self.classes, &target)
+        } else {
+            found
+        };
+        if found.is_empty() {
+            ConnectionTarget::Unresolved
+        } else {
+            ConnectionTarget::Entities(found)
+        }
+    }
+}
+
+fn find(map: &HashMap<String, Vec<usize>>, target: &str) -> Vec<usize> {
+    let mut found = match target.strip_suffix('*') {
+        Some(prefix) => map
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .flat_map(|(_, indices)| indices.iter().copied())
+            .collect(),
+        None => map.get(target).cloned().unwrap_or_default(),
+    };
+    found.sort_unstable();
+    found
+}
+
+impl Entities {
+    /// Build the graph of io connections between the entities
+    pub fn graph(&self) -> EntityGraph {
+        EntityGraph::new(self)
+    }
+}
+
+#[test]
+fn test_entity_graph() {
+    let entities = Entities::new(
+        r#"
+{
+"classname" "trigger_once"
+"OnStartTouch" "Door1,Open,,0,-1"
+"OnStartTouch" "relay_*,Trigger,,1.5,1"
+"OnTrigger" "!activator\x1bSetHealth\x1b100\x1b0\x1b-1"
+"OnTrigger" "!self,Kill,,0,1"
+"OnTrigger" "missing,Kill,,0,1"
+}
+{
+"classname" "func_door"
+"targetname" "door1"
+}
+{
+"classname" "logic_relay"
+"targetname" "relay_a"
+"OnTrigger" "player,Kill,,0,-1"
+}
+{
+"classname" "player"
+}
+"#
+        .replace("\\x1b", "\x1b"),
+    );
+    let graph = entities.graph();
+    assert_eq!(4, graph.entities().len());
+    assert_eq!(6, graph.connections().len());
+
+    let outgoing: Vec<_> = graph.outgoing(0).map(|c| &c.target).collect();
+    assert_eq!(
+        vec![
+            &ConnectionTarget::Entities(vec![1]),
+            &ConnectionTarget::Entities(vec![2]),
+            &ConnectionTarget::Special(SpecialTarget::Activator),
+            &ConnectionTarget::Entities(vec![0]),
+            &ConnectionTarget::Unresolved,
+        ],
+        outgoing
+    );
+
+    let incoming: Vec<_> = graph.incoming(1).map(|c| c.output.as_str()).collect();
+    assert_eq!(vec!["OnStartTouch"], incoming);
+    assert_eq!(1, graph.incoming(3).count());
+    assert_eq!(
+        vec!["missing"],
+        graph
+            .unresolved()
+            .map(|c| c.connection.target.as_str())
+            .collect::<Vec<_>>()
+    );
+}
@@ -2,31 +2,50 @@ mod displacement;
 mod entity;
 mod game;
 mod leaves;
+mod logic;
+mod occlusion;
+mod overlay;
+mod packfile;
+mod phys;
 mod prop;
+mod stripper;
+mod vhv;
+mod vmt;
+mod vpk;
+mod vtf;
 
 pub use self::displacement::*;
 pub use self::entity::*;
 pub use self::game::*;
 pub use self::leaves::*;
+pub use self::logic::*;
+pub use self::occlusion::*;
+pub use self::overlay::*;
+pub use self::packfile::*;
+pub use self::phys::*;
+pub use self::prop::PropEntity;
+pub use self::stripper::*;
+pub use self::vhv::*;
+pub use self::vmt::*;
+pub use self::vpk::*;
+pub use self::vtf::*;
 use crate::bspfile::LumpType;
-use crate::{BspResult, StringError};
+use crate::StringError;
 use arrayvec::ArrayString;
 use binrw::error::CustomError;
 use binrw::{BinRead, BinResult, Endian};
 use bitflags::bitflags;
 use bv::BitVec;
 use num_enum::{TryFromPrimitive, TryFromPrimitiveError};
-use std::borrow::Cow;
 use std::cmp::min;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
-use std::io::{Cursor, Read, Seek};
+use std::io::{Read, Seek};
 use std::mem::size_of;
 use std::ops::Index;
-use std::sync::Mutex;
-pub use vbsp_common::{Angles, Color, EntityProp, LightColor, Negated, PropPlacement, Vector};
-use zip::result::ZipError;
-use zip::ZipArchive;
+pub use vbsp_common::{
+    Angles, Color, EntityConnection, EntityProp, LightColor, Negated, PropPlacement, Vector,
+};
 
 /// Validate that reading the type consumes `size_of::<T>()` bytes
 #[cfg(test)]
@@ -45,6 +64,7 @@ where
 {
     use binrw::BinReaderExt;
     use std::any::type_name;
+    use std::io::Cursor;
 
     let bytes = [0; 512];
     let mut reader = Cursor::new(bytes);
@@ -451,14 +471,117 @@ pub struct Face {
     pub smoothing_groups: u32,
 }
 
+/// The top bit of `Face::primitive_count` is used as a flag
+const FACE_NO_DYNAMIC_SHADOWS: u16 = 0x8000;
+
 impl Face {
     pub fn displacement_index(&self) -> Option<i16> {
         (self.displacement_info >= 0).then_some(self.displacement_info)
     }
+
+    /// Get the number of primitives of the face, without the dynamic shadows flag
+    pub fn primitive_count(&self) -> u16 {
+        self.primitive_count & !FACE_NO_DYNAMIC_SHADOWS
+    }
+
+    pub fn dynamic_shadows_disabled(&self) -> bool {
+        self.primitive_count & FACE_NO_DYNAMIC_SHADOWS != 0
+    }
+}
+
+#[test]
+fn test_face_primitive_count() {
+    use std::io::Cursor;
+
+    let mut data = [0; 56];
+    data[48..50].copy_from_slice(&0x8003u16.to_le_bytes());
+    data[50..52].copy_from_slice(&12u16.to_le_bytes());
+    let face = Face::read_le(&mut Cursor::new(data)).unwrap();
+    assert_eq!(3, face.primitive_count());
+    assert_eq!(12, face.first_primitive_index);
+    assert!(face.dynamic_shadows_disabled());
+
+    data[48..50].copy_from_slice(&2u16.to_le_bytes());
+    let face = Face::read_le(&mut Cursor::new(data)).unwrap();
+    assert_eq!(2, face.primitive_count());
+    assert!(!face.dynamic_shadows_disabled());
 }
 
 static_assertions::const_assert_eq!(size_of::<Face>(), 56);
 
+#[derive(Debug, Clone, BinRead)]
+pub struct Primitive {
+    #[br(pad_after = 1)]
+    pub ty: PrimitiveType,
+    pub first_index: u16,
+    pub index_count: u16,
+    pub first_vertex: u16,
+    pub vertex_count: u16,
+}
+
+static_assertions::const_assert_eq!(size_of::<Primitive>(), 10);
+
+#[test]
+fn test_primitive_bytes() {
+    test_read_bytes::<Primitive>();
+}
+
+impl Primitive {
+    /// Convert a list of indices for this primitive into triangles
+    pub fn triangles(&self, indices: &[u16]) -> Vec<[u16; 3]> {
+        match self.ty {
+            PrimitiveType::TriangleList => indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+            PrimitiveType::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, triangle)| {
+                    if i % 2 == 0 {
+                        [triangle[0], triangle[1], triangle[2]]
+                    } else {
+                        [triangle[1], triangle[0], triangle[2]]
+                    }
+                })
+                .filter(|[a, b, c]| a != b && b != c && a != c)
+                .collect(),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(BinRead, Debug, Copy, Clone, PartialEq, Eq)]
+#[br(repr = u8)]
+pub enum PrimitiveType {
+    TriangleList = 0,
+    TriangleStrip = 1,
+}
+
+#[test]
+fn test_primitive_triangles() {
+    let primitive = |ty| Primitive {
+        ty,
+        first_index: 0,
+        index_count: 6,
+        first_vertex: 0,
+        vertex_count: 0,
+    };
+    assert_eq!(
+        vec![[0, 1, 2], [2, 3, 4]],
+        primitive(PrimitiveType::TriangleList).triangles(&[0, 1, 2, 2, 3, 4])
+    );
+    assert_eq!(
+        vec![[0, 1, 2], [2, 1, 3], [2, 3, 4]],
+        primitive(PrimitiveType::TriangleStrip).triangles(&[0, 1, 2, 3, 4])
+    );
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct PrimitiveVertex {
+    pub position: Vector,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct VisData {
     pub cluster_count: u32,
@@ -499,85 +622,72 @@ impl VisData {
     }
 }
 
-#[derive(Debug, Clone, BinRead)]
-pub struct VertNormal {
-    pub normal: f32,
-}
+/// Default resolution of cubemaps which don't specify a size
+pub const DEFAULT_CUBEMAP_RESOLUTION: u32 = 32;
 
 #[derive(Debug, Clone, BinRead)]
-pub struct VertNormalIndex {
-    pub index: i16,
+pub struct CubeMapSample {
+    pub origin: [i32; 3],
+    pub size: i32,
 }
 
-pub struct Packfile {
-    zip: Mutex<ZipArchive<Cursor<Vec<u8>>>>,
-}
+static_assertions::const_assert_eq!(size_of::<CubeMapSample>(), 16);
 
-impl Clone for Packfile {
-    fn clone(&self) -> Self {
-        Packfile {
-            zip: Mutex::new(self.zip.lock().unwrap().clone()),
-        }
+impl CubeMapSample {
+    pub fn origin(&self) -> Vector {
+        self.origin.map(|coord| coord as f32).into()
     }
-}
 
-impl Debug for Packfile {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Packfile")
-            .field(
-                "zip",
-                &self
-                    .zip
-                    .lock()
-                    .unwrap()
-                    .file_names()
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )
-            .finish()
+    /// Get the resolution of the sides of the cubemap texture
+    ///
+    /// Returns `None` if the size is too large to be valid
+    pub fn resolution(&self) -> Option<u32> {
+        match self.size {
+            1.. => 1u32.checked_shl(self.size as u32 - 1),
+            _ => Some(DEFAULT_CUBEMAP_RESOLUTION),
+        }
     }
-}
 
-impl Packfile {
-    pub fn read(data: Cow<[u8]>) -> BspResult<Self> {
-        let reader = Cursor::new(data.into_owned());
-        let zip = Mutex::new(ZipArchive::new(reader)?);
-        Ok(Packfile { zip })
+    /// Get the path of the cubemap texture in the packfile
+    ///
+    /// `map_name` is the name of the bsp file, without the `.bsp` extension
+    pub fn texture_path(&self, map_name: &str, hdr: bool) -> String {
+        let [x, y, z] = self.origin;
+        let extension = if hdr { "hdr.vtf" } else { "vtf" };
+        format!("materials/maps/{map_name}/c{x}_{y}_{z}.{extension}")
     }
+}
 
-    pub fn get(&self, name: &str) -> BspResult<Option<Vec<u8>>> {
-        let mut zip = self.zip.lock().unwrap();
-        let mut entry = match zip.by_name(name) {
-            Ok(entry) => entry,
-            Err(ZipError::FileNotFound) => {
-                return Ok(None);
-            }
-            Err(e) => {
-                return Err(e.into());
-            }
-        };
-        let mut buff = vec![0; entry.size() as usize];
-        entry.read_exact(&mut buff)?;
-        Ok(Some(buff))
-    }
+#[test]
+fn test_cubemap_texture_path() {
+    let sample = CubeMapSample {
+        origin: [-128, 64, 0],
+        size: 7,
+    };
+    assert_eq!(Some(64), sample.resolution());
+    assert_eq!(
+        Some(DEFAULT_CUBEMAP_RESOLUTION),
+        CubeMapSample { size: 0, ..sample }.resolution()
+    );
+    assert_eq!(None, CubeMapSample { size: 40, ..sample }.resolution());
+    assert_eq!(
+        "materials/maps/pl_upward/c-128_64_0.vtf",
+        sample.texture_path("pl_upward", false)
+    );
+    assert_eq!(
+        "materials/maps/pl_upward/c-128_64_0.hdr.vtf",
+        sample.texture_path("pl_upward", true)
+    );
+}
 
-    pub fn has(&self, name: &str) -> BspResult<bool> {
-        let mut zip = self.zip.lock().unwrap();
-        let result = match zip.by_name(name) {
-            Ok(_) => Ok(true),
-            Err(ZipError::FileNotFound) => {
-                return Ok(false);
-            }
-            Err(e) => {
-                return Err(e.into());
-            }
-        };
-        result
-    }
+#[derive(Debug, Clone, BinRead)]
+pub struct VertNormal {
+    pub normal: f32,
+}
 
-    pub fn into_zip(self) -> Mutex<ZipArchive<Cursor<Vec<u8>>>> {
-        self.zip
-    }
+#[derive(Debug, Clone, BinRead)]
+pub struct VertNormalIndex {
+    pub index: i16,
 }
 
 fn try_read_enum<Enum, Reader, Error, ErrorFn>(
@@ -0,0 +1,93 @@
+use super::LumpArgs;
+use crate::error::UnsupportedLumpVersion;
+use crate::Vector;
+use binrw::{BinRead, BinResult, Endian};
+use bitflags::bitflags;
+use std::io::{Read, Seek};
+use std::mem::size_of;
+
+/// The `func_occluder` data compiled into the bsp
+#[derive(Debug, Clone, Default)]
+pub struct OcclusionLump {
+    pub occluders: Vec<Occluder>,
+    pub polygons: Vec<OccluderPolygon>,
+    pub vertex_indices: Vec<i32>,
+}
+
+impl BinRead for OcclusionLump {
+    type Args<'a> = LumpArgs;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        if args.length < size_of::<i32>() {
+            return Ok(OcclusionLump::default());
+        }
+        if args.version > 2 {
+            return Err(binrw::Error::Custom {
+                err: Box::new(UnsupportedLumpVersion {
+                    lump_type: "occlusion",
+                    version: args.version as u16,
+                }),
+                pos: reader.stream_position()?,
+            });
+        }
+
+        let occluder_count = i32::read_options(reader, endian, ())?;
+        let occluders = (0..occluder_count)
+            .map(|_| Occluder::read_options(reader, endian, (args.version,)))
+            .collect::<BinResult<Vec<_>>>()?;
+        let polygon_count = i32::read_options(reader, endian, ())?;
+        let polygons = (0..polygon_count)
+            .map(|_| OccluderPolygon::read_options(reader, endian, ()))
+            .collect::<BinResult<Vec<_>>>()?;
+        let vertex_index_count = i32::read_options(reader, endian, ())?;
+        let vertex_indices = (0..vertex_index_count)
+            .map(|_| i32::read_options(reader, endian, ()))
+            .collect::<BinResult<Vec<_>>>()?;
+
+        Ok(OcclusionLump {
+            occluders,
+            polygons,
+            vertex_indices,
+        })
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(import(version: u32))]
+pub struct Occluder {
+    pub flags: OccluderFlags,
+    pub first_polygon: i32,
+    pub polygon_count: i32,
+    pub mins: Vector,
+    pub maxs: Vector,
+    /// The area the occluder is in, only stored since lump version 2
+    #[br(if(version >= 2))]
+    pub area: i32,
+}
+
+#[test]
+fn test_occluder_bytes() {
+    super::test_read_bytes_args::<Occluder>((2,));
+}
+
+#[derive(BinRead, Debug, Clone, Copy)]
+pub struct OccluderFlags(u32);
+
+bitflags! {
+    impl OccluderFlags: u32 {
+        const INACTIVE = 0x1;
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct OccluderPolygon {
+    pub first_vertex_index: i32,
+    pub vertex_count: i32,
+    pub plane: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<OccluderPolygon>(), 12);
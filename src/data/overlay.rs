@@ -0,0 +1,237 @@
+use crate::Vector;
+use binrw::BinRead;
+use std::mem::size_of;
+
+/// Maximum number of faces a single overlay can be applied to
+pub const OVERLAY_FACE_COUNT: usize = 64;
+/// Maximum number of faces a single water overlay can be applied to
+pub const WATER_OVERLAY_FACE_COUNT: usize = 256;
+
+const OVERLAY_RENDER_ORDER_BITS: u16 = 2;
+const OVERLAY_FACE_COUNT_MASK: u16 = (1 << (16 - OVERLAY_RENDER_ORDER_BITS)) - 1;
+
+/// An `info_overlay` compiled into the bsp
+#[derive(Debug, Clone, BinRead)]
+pub struct Overlay<const FACE_COUNT: usize = OVERLAY_FACE_COUNT> {
+    pub id: i32,
+    pub texture_info: i16,
+    pub face_count_and_render_order: u16,
+    pub faces: [i32; FACE_COUNT],
+    pub u: [f32; 2],
+    pub v: [f32; 2],
+    /// The corners of the overlay in the (u, v) plane of the basis
+    ///
+    /// The z components are used to store the u basis vector and whether the v basis is flipped
+    pub uv_points: [Vector; 4],
+    pub origin: Vector,
+    pub basis_normal: Vector,
+}
+
+/// An `info_overlay` applied to water surfaces
+pub type WaterOverlay = Overlay<WATER_OVERLAY_FACE_COUNT>;
+
+static_assertions::const_assert_eq!(size_of::<Overlay>(), 352);
+static_assertions::const_assert_eq!(size_of::<WaterOverlay>(), 1120);
+
+#[test]
+fn test_overlay_bytes() {
+    super::test_read_bytes::<Overlay>();
+}
+
+impl<const FACE_COUNT: usize> Overlay<FACE_COUNT> {
+    pub fn face_count(&self) -> usize {
+        (self.face_count_and_render_order & OVERLAY_FACE_COUNT_MASK) as usize
+    }
+
+    pub fn render_order(&self) -> u8 {
+        (self.face_count_and_render_order >> (16 - OVERLAY_RENDER_ORDER_BITS)) as u8
+    }
+
+    /// Get the indexes of the faces this overlay is applied to
+    pub fn face_indexes(&self) -> &[i32] {
+        &self.faces[..self.face_count().min(FACE_COUNT)]
+    }
+
+    /// Get the u, v and normal basis vectors of the overlay
+    pub fn basis(&self) -> [Vector; 3] {
+        let normal = self.basis_normal;
+        let u = Vector {
+            x: self.uv_points[0].z,
+            y: self.uv_points[1].z,
+            z: self.uv_points[2].z,
+        };
+        let v = normal.cross(u);
+        let v = if self.uv_points[3].z == 1.0 {
+            v * -1.0
+        } else {
+            v
+        };
+        [u, v, normal]
+    }
+
+    /// Get the world positions of the four corners of the overlay
+    pub fn corners(&self) -> [Vector; 4] {
+        let [u, v, _] = self.basis();
+        self.uv_points
+            .map(|point| self.origin + u * point.x + v * point.y)
+    }
+
+    /// Get the texture coordinates of the four corners of the overlay
+    pub fn corner_uvs(&self) -> [[f32; 2]; 4] {
+        [
+            [self.u[0], self.v[0]],
+            [self.u[0], self.v[1]],
+            [self.u[1], self.v[1]],
+            [self.u[1], self.v[0]],
+        ]
+    }
+
+    /// Clip a convex polygon against the overlay and calculate the texture coordinates of the result
+    ///
+    /// The polygon is expected to be (roughly) in the plane the overlay is projected on
+    pub fn project(&self, polygon: impl IntoIterator<Item = Vector>) -> Vec<OverlayVertex> {
+        let [u, v, _] = self.basis();
+        let quad = self.uv_points.map(|point| [point.x, point.y]);
+        let orientation = (0..4)
+            .map(|i| {
+                let [a, b] = [quad[i], quad[(i + 1) % 4]];
+                a[0] * b[1] - b[0] * a[1]
+            })
+            .sum::<f32>()
+            .signum();
+
+        let mut points: Vec<(Vector, [f32; 2])> = polygon
+            .into_iter()
+            .map(|point| {
+                let relative = point - self.origin;
+                (point, [relative.dot(u), relative.dot(v)])
+            })
+            .collect();
+
+        for i in 0..4 {
+            let [a, b] = [quad[i], quad[(i + 1) % 4]];
+            let side = |p: [f32; 2]| {
+                ((b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])) * orientation
+            };
+
+            let mut clipped = Vec::with_capacity(points.len() + 1);
+            for (j, current) in points.iter().enumerate() {
+                let next = points[(j + 1) % points.len()];
+                let current_side = side(current.1);
+                let next_side = side(next.1);
+                if current_side >= 0.0 {
+                    clipped.push(*current);
+                }
+                if (current_side > 0.0 && next_side < 0.0)
+                    || (current_side < 0.0 && next_side > 0.0)
+                {
+                    let t = current_side / (current_side - next_side);
+                    clipped.push((
+                        current.0 + (next.0 - current.0) * t,
+                        [
+                            current.1[0] + (next.1[0] - current.1[0]) * t,
+                            current.1[1] + (next.1[1] - current.1[1]) * t,
+                        ],
+                    ));
+                }
+            }
+            points = clipped;
+        }
+
+        let uvs = self.corner_uvs();
+        points
+            .into_iter()
+            .map(|(position, point)| {
+                let [a, b, c] = barycentric(point, [quad[0], quad[1], quad[2]]);
+                let (weights, corners) = if a.min(b).min(c) >= -0.001 {
+                    ([a, b, c], [uvs[0], uvs[1], uvs[2]])
+                } else {
+                    (
+                        barycentric(point, [quad[0], quad[2], quad[3]]),
+                        [uvs[0], uvs[2], uvs[3]],
+                    )
+                };
+                let uv = [0, 1].map(|axis| {
+                    weights
+                        .iter()
+                        .zip(corners)
+                        .map(|(weight, corner)| weight * corner[axis])
+                        .sum()
+                });
+                OverlayVertex { position, uv }
+            })
+            .collect()
+    }
+}
+
+fn barycentric(point: [f32; 2], [a, b, c]: [[f32; 2]; 3]) -> [f32; 3] {
+    let det = (b[1] - c[1]) * (a[0] - c[0]) + (c[0] - b[0]) * (a[1] - c[1]);
+    if det == 0.0 {
+        return [1.0, 0.0, 0.0];
+    }
+    let wa = ((b[1] - c[1]) * (point[0] - c[0]) + (c[0] - b[0]) * (point[1] - c[1])) / det;
+    let wb = ((c[1] - a[1]) * (point[0] - c[0]) + (a[0] - c[0]) * (point[1] - c[1])) / det;
+    [wa, wb, 1.0 - wa - wb]
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct OverlayFade {
+    pub fade_distance_min_squared: f32,
+    pub fade_distance_max_squared: f32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct OverlaySystemLevel {
+    pub min_cpu_level: u8,
+    pub max_cpu_level: u8,
+    pub min_gpu_level: u8,
+    pub max_gpu_level: u8,
+}
+
+/// A vertex of an overlay projected onto a face
+#[derive(Debug, Clone, Copy)]
+pub struct OverlayVertex {
+    pub position: Vector,
+    pub uv: [f32; 2],
+}
+
+#[test]
+fn test_overlay_project() {
+    let overlay = Overlay::<1> {
+        id: 0,
+        texture_info: 0,
+        face_count_and_render_order: 1,
+        faces: [0],
+        u: [0.0, 1.0],
+        v: [0.0, 1.0],
+        uv_points: [
+            [-1.0, -1.0, 1.0].into(),
+            [-1.0, 1.0, 0.0].into(),
+            [1.0, 1.0, 0.0].into(),
+            [1.0, -1.0, 0.0].into(),
+        ],
+        origin: [0.0, 0.0, 0.0].into(),
+        basis_normal: [0.0, 0.0, 1.0].into(),
+    };
+
+    // a face covering the upper right quadrant of the overlay and extending past it
+    let projected = overlay.project([
+        [0.0, 0.0, 0.0].into(),
+        [0.0, 4.0, 0.0].into(),
+        [4.0, 4.0, 0.0].into(),
+        [4.0, 0.0, 0.0].into(),
+    ]);
+
+    let positions: Vec<[f32; 3]> = projected.iter().map(|vert| vert.position.into()).collect();
+    assert_eq!(
+        vec![
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0]
+        ],
+        positions
+    );
+    let uvs: Vec<[f32; 2]> = projected.iter().map(|vert| vert.uv).collect();
+    assert_eq!(vec![[0.5, 0.5], [0.5, 1.0], [1.0, 1.0], [1.0, 0.5]], uvs);
+}
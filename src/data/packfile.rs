@@ -0,0 +1,769 @@
+use crate::{BspError, BspResult};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::fs::{create_dir_all, write};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+
+/// The zip file embedded in the bsp
+///
+/// The files are read directly from the in-memory zip using an index of the central directory,
+/// cloning a packfile only clones a reference to the shared data.
+#[derive(Clone)]
+pub struct Packfile {
+    data: Arc<[u8]>,
+    index: Arc<PackfileIndex>,
+}
+
+static_assertions::assert_impl_all!(Packfile: Send, Sync);
+
+impl Debug for Packfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Packfile")
+            .field(
+                "zip",
+                &self
+                    .index
+                    .entries
+                    .iter()
+                    .map(|entry| entry.entry.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .finish()
+    }
+}
+
+impl Packfile {
+    pub fn read(data: Cow<[u8]>) -> BspResult<Self> {
+        let data: Arc<[u8]> = data.into();
+        let index = Arc::new(PackfileIndex::read(&data)?);
+        Ok(Packfile { data, index })
+    }
+
+    pub fn get(&self, name: &str) -> BspResult<Option<Vec<u8>>> {
+        match self.index.get(name) {
+            Some(entry) => Ok(Some(entry.decompress(&self.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn has(&self, name: &str) -> BspResult<bool> {
+        Ok(self.index.get(name).is_some())
+    }
+
+    /// Get a file from the packfile, ignoring the case of the name
+    ///
+    /// The engine looks up files case-insensitively, and maps often pack files with different
+    /// casing than the materials or models referencing them.
+    pub fn get_ignore_case(&self, name: &str) -> BspResult<Option<Vec<u8>>> {
+        match self.index.get_ignore_case(name) {
+            Some(entry) => Ok(Some(entry.decompress(&self.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Check if the packfile contains a file, ignoring the case of the name
+    pub fn has_ignore_case(&self, name: &str) -> BspResult<bool> {
+        Ok(self.index.get_ignore_case(name).is_some())
+    }
+
+    /// Get information about all files in the packfile
+    pub fn entries(&self) -> impl Iterator<Item = &PackfileEntry> {
+        self.index
+            .entries
+            .iter()
+            .map(|entry| &entry.entry)
+            .filter(|entry| !entry.is_dir())
+    }
+
+    /// Get information about all files in the packfile with a path matching a glob pattern
+    ///
+    /// `*` matches any number of characters within a path segment, `**/` matches any number of
+    /// directories, `**` otherwise matches any number of characters including `/` and `?` matches
+    /// a single character. Matching is case-insensitive.
+    pub fn entries_matching<'a>(
+        &'a self,
+        pattern: &'a str,
+    ) -> impl Iterator<Item = &'a PackfileEntry> + 'a {
+        self.entries()
+            .filter(move |entry| glob_match(pattern, &entry.name))
+    }
+
+    /// Get information about all files in the packfile inside a directory
+    pub fn entries_in(&self, directory: &str) -> impl Iterator<Item = &PackfileEntry> + use<'_> {
+        let prefix = directory.trim_end_matches('/').to_ascii_lowercase() + "/";
+        self.entries()
+            .filter(move |entry| entry.name.to_ascii_lowercase().starts_with(&prefix))
+    }
+
+    /// Extract all files in the packfile into a directory
+    ///
+    /// Returns the number of extracted files
+    pub fn extract_to(&self, target: impl AsRef<Path>) -> BspResult<usize> {
+        self.extract_entries_to(target.as_ref(), self.entries())
+    }
+
+    /// Extract all files in the packfile with a path matching a glob pattern into a directory
+    ///
+    /// See [`Packfile::entries_matching`] for the supported patterns.
+    /// Returns the number of extracted files
+    pub fn extract_matching_to(&self, target: impl AsRef<Path>, pattern: &str) -> BspResult<usize> {
+        self.extract_entries_to(target.as_ref(), self.entries_matching(pattern))
+    }
+
+    fn extract_entries_to<'a>(
+        &self,
+        target: &Path,
+        entries: impl Iterator<Item = &'a PackfileEntry>,
+    ) -> BspResult<usize> {
+        // check all paths before writing anything, so a malicious packfile can't leave a partial extraction
+        let files = entries
+            .map(|entry| {
+                let path = target.join(safe_relative_path(&entry.name)?);
+                Ok((entry.name.as_str(), path))
+            })
+            .collect::<BspResult<Vec<_>>>()?;
+
+        let mut written = 0;
+        for (name, path) in &files {
+            let Some(data) = self.get(name)? else {
+                continue;
+            };
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)?;
+            }
+            write(path, data)?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Add a file to the packfile, replacing any existing file with the same name
+    ///
+    /// Note that this re-builds the entire packfile
+    pub fn insert(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        compression: PackfileCompression,
+    ) -> BspResult<()> {
+        let file = RawFile::new(name.replace('\\', "/"), data, compression)?;
+        let mut files = self.raw_files();
+        match files.iter_mut().find(|existing| existing.name == file.name) {
+            Some(existing) => *existing = file,
+            None => files.push(file),
+        }
+        let bytes = write_zip(&files);
+        self.set_data(bytes)
+    }
+
+    /// Remove a file from the packfile, returns whether the file existed
+    ///
+    /// Note that this re-builds the entire packfile
+    pub fn remove(&mut self, name: &str) -> BspResult<bool> {
+        let name = name.replace('\\', "/");
+        let mut files = self.raw_files();
+        let count = files.len();
+        files.retain(|file| file.name != name);
+        if files.len() == count {
+            return Ok(false);
+        }
+        let bytes = write_zip(&files);
+        self.set_data(bytes)?;
+        Ok(true)
+    }
+
+    /// Serialize the packfile into a zip file as expected by the source engine
+    pub fn to_bytes(&self) -> Vec<u8> {
+        write_zip(&self.raw_files())
+    }
+
+    fn raw_files(&self) -> Vec<RawFile<'_>> {
+        self.index
+            .entries
+            .iter()
+            .filter(|entry| !entry.entry.is_dir())
+            .map(|entry| RawFile {
+                name: entry.entry.name.clone(),
+                compression: entry.entry.compression,
+                crc32: entry.crc32,
+                size: entry.entry.size as u32,
+                data: Cow::Borrowed(entry.raw_data(&self.data)),
+            })
+            .collect()
+    }
+
+    fn set_data(&mut self, data: Vec<u8>) -> BspResult<()> {
+        *self = Packfile::read(Cow::Owned(data))?;
+        Ok(())
+    }
+
+    /// Get the raw bytes of the zip file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Open the packfile with the `zip` crate
+    ///
+    /// This can fail even if the packfile could be read, since the `zip` crate is stricter
+    pub fn into_zip(self) -> BspResult<ZipArchive<Cursor<Vec<u8>>>> {
+        Ok(ZipArchive::new(Cursor::new(self.data.to_vec()))?)
+    }
+}
+
+/// Index of the central directory of the zip file
+#[derive(Debug)]
+struct PackfileIndex {
+    entries: Vec<IndexEntry>,
+    names: HashMap<String, usize>,
+    /// Entry indices by lowercase name, the first entry wins if multiple names only differ in case
+    lowercase_names: HashMap<String, usize>,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    entry: PackfileEntry,
+    crc32: u32,
+    data_start: usize,
+}
+
+impl PackfileIndex {
+    fn read(data: &[u8]) -> BspResult<Self> {
+        let end = find_end_of_central_directory(data)?;
+        let entry_count = read_u16(data, end + 10)? as usize;
+        let mut offset = read_u32(data, end + 16)? as usize;
+
+        let mut entries = Vec::with_capacity(entry_count);
+        let mut names = HashMap::with_capacity(entry_count);
+        let mut lowercase_names = HashMap::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            if read_u32(data, offset)? != CENTRAL_DIRECTORY_SIGNATURE {
+                return Err(ZipError::InvalidArchive("Invalid central directory header").into());
+            }
+            let compression = PackfileCompression::from(read_u16(data, offset + 10)?);
+            let crc32 = read_u32(data, offset + 16)?;
+            let compressed_size = read_u32(data, offset + 20)? as u64;
+            let size = read_u32(data, offset + 24)? as u64;
+            let name_length = read_u16(data, offset + 28)? as usize;
+            let extra_length = read_u16(data, offset + 30)? as usize;
+            let comment_length = read_u16(data, offset + 32)? as usize;
+            let header_start = read_u32(data, offset + 42)? as usize;
+            let name_start = offset + CENTRAL_DIRECTORY_HEADER_SIZE;
+            let name =
+                data.get(name_start..name_start + name_length)
+                    .ok_or(ZipError::InvalidArchive(
+                        "Central directory entry out of bounds",
+                    ))?;
+            let name = String::from_utf8_lossy(name).into_owned();
+
+            if read_u32(data, header_start)? != LOCAL_HEADER_SIGNATURE {
+                return Err(ZipError::InvalidArchive("Invalid local file header").into());
+            }
+            let data_start = header_start
+                + LOCAL_HEADER_SIZE
+                + read_u16(data, header_start + 26)? as usize
+                + read_u16(data, header_start + 28)? as usize;
+            if data.len() < data_start + compressed_size as usize {
+                return Err(ZipError::InvalidArchive("File data out of bounds").into());
+            }
+
+            names.insert(name.clone(), entries.len());
+            lowercase_names
+                .entry(name.to_ascii_lowercase())
+                .or_insert(entries.len());
+            entries.push(IndexEntry {
+                entry: PackfileEntry {
+                    name,
+                    size,
+                    compressed_size,
+                    compression,
+                },
+                crc32,
+                data_start,
+            });
+            offset = name_start + name_length + extra_length + comment_length;
+        }
+
+        Ok(PackfileIndex {
+            entries,
+            names,
+            lowercase_names,
+        })
+    }
+
+    fn get(&self, name: &str) -> Option<&IndexEntry> {
+        self.names.get(name).map(|index| &self.entries[*index])
+    }
+
+    fn get_ignore_case(&self, name: &str) -> Option<&IndexEntry> {
+        self.get(name).or_else(|| {
+            self.lowercase_names
+                .get(&name.to_ascii_lowercase())
+                .map(|index| &self.entries[*index])
+        })
+    }
+}
+
+impl IndexEntry {
+    fn raw_data<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.data_start..self.data_start + self.entry.compressed_size as usize]
+    }
+
+    fn decompress(&self, data: &[u8]) -> BspResult<Vec<u8>> {
+        let raw = self.raw_data(data);
+        let decompressed = match self.entry.compression {
+            PackfileCompression::Stored => raw.to_vec(),
+            PackfileCompression::Lzma => {
+                lzma_decompress(raw, self.entry.size).map_err(|error| {
+                    BspError::PackfileDecompress {
+                        name: self.entry.name.clone(),
+                        error,
+                    }
+                })?
+            }
+            PackfileCompression::Other(_) => {
+                return Err(
+                    ZipError::UnsupportedArchive("Compression method not supported").into(),
+                );
+            }
+        };
+        if decompressed.len() as u64 != self.entry.size
+            || crc32fast::hash(&decompressed) != self.crc32
+        {
+            return Err(ZipError::InvalidArchive("Invalid checksum").into());
+        }
+        Ok(decompressed)
+    }
+}
+
+fn find_end_of_central_directory(data: &[u8]) -> BspResult<usize> {
+    // the end of central directory record is followed by a comment of at most 64k
+    let search_start = data
+        .len()
+        .saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE + u16::MAX as usize);
+    (search_start..=data.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+        .rev()
+        .find(|offset| read_u32(data, *offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| ZipError::InvalidArchive("Could not find central directory end").into())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> BspResult<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| ZipError::InvalidArchive("Unexpected end of zip data").into())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> BspResult<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| ZipError::InvalidArchive("Unexpected end of zip data").into())
+}
+
+/// The maximum ratio between the compressed size and the capacity allocated for the decompressed data
+///
+/// The uncompressed size comes from the packfile, so it can't be trusted for the initial allocation
+const MAX_PREALLOCATE_RATIO: usize = 4;
+
+/// Decompress the data of an lzma zip entry
+fn lzma_decompress(data: &[u8], size: u64) -> Result<Vec<u8>, lzma_rs::error::Error> {
+    use lzma_rs::decompress::{Options, UnpackedSize};
+
+    // skip the lzma sdk version and properties size
+    let stream = data.get(4..).ok_or(lzma_rs::error::Error::LzmaError(
+        "Invalid LZMA header".into(),
+    ))?;
+    let capacity = (size as usize).min(data.len() * MAX_PREALLOCATE_RATIO);
+    let mut output = Vec::with_capacity(capacity);
+    lzma_rs::lzma_decompress_with_options(
+        &mut Cursor::new(stream),
+        &mut output,
+        &Options {
+            unpacked_size: UnpackedSize::UseProvided(Some(size)),
+            memlimit: None,
+            allow_incomplete: false,
+        },
+    )?;
+    Ok(output)
+}
+
+#[test]
+fn test_lzma_decompress_untrusted_size() {
+    let compressed = lzma_compress(b"hello").unwrap();
+    assert_eq!(b"hello".to_vec(), lzma_decompress(&compressed, 5).unwrap());
+    // a huge size from a malformed header shouldn't be allocated up front
+    assert!(lzma_decompress(&compressed, u64::MAX / 2).is_err());
+    assert!(lzma_decompress(&[9, 20], 5).is_err());
+}
+
+/// Information about a file stored in the packfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackfileEntry {
+    pub name: String,
+    /// Uncompressed size of the file
+    pub size: u64,
+    pub compressed_size: u64,
+    pub compression: PackfileCompression,
+}
+
+impl PackfileEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackfileCompression {
+    Stored,
+    Lzma,
+    Other(u16),
+}
+
+impl PackfileCompression {
+    fn method(&self) -> u16 {
+        match self {
+            PackfileCompression::Stored => 0,
+            PackfileCompression::Lzma => 14,
+            PackfileCompression::Other(method) => *method,
+        }
+    }
+}
+
+impl From<u16> for PackfileCompression {
+    fn from(method: u16) -> Self {
+        match method {
+            0 => PackfileCompression::Stored,
+            14 => PackfileCompression::Lzma,
+            method => PackfileCompression::Other(method),
+        }
+    }
+}
+
+/// A file in the packfile with its data in its compressed form
+struct RawFile<'a> {
+    name: String,
+    compression: PackfileCompression,
+    crc32: u32,
+    size: u32,
+    data: Cow<'a, [u8]>,
+}
+
+impl RawFile<'_> {
+    fn new(name: String, data: &[u8], compression: PackfileCompression) -> BspResult<Self> {
+        let compressed = match compression {
+            PackfileCompression::Stored => data.to_vec(),
+            PackfileCompression::Lzma => lzma_compress(data)?,
+            PackfileCompression::Other(method) => {
+                return Err(BspError::UnsupportedPackfileCompression(method));
+            }
+        };
+        Ok(RawFile {
+            name,
+            compression,
+            crc32: crc32fast::hash(data),
+            size: data.len() as u32,
+            data: Cow::Owned(compressed),
+        })
+    }
+
+    fn version_needed(&self) -> u16 {
+        match self.compression {
+            PackfileCompression::Lzma => 63,
+            _ => 10,
+        }
+    }
+}
+
+/// Compress data in the format used for lzma zip entries
+///
+/// The entries start with the lzma sdk version and the size of the properties, followed by the
+/// properties and the compressed stream without the uncompressed size or an end marker.
+fn lzma_compress(data: &[u8]) -> BspResult<Vec<u8>> {
+    use lzma_rs::compress::{Options, UnpackedSize};
+
+    let mut lzma = Vec::with_capacity(data.len() + 13);
+    lzma_rs::lzma_compress_with_options(
+        &mut Cursor::new(data),
+        &mut lzma,
+        &Options {
+            unpacked_size: UnpackedSize::WriteToHeader(Some(data.len() as u64)),
+        },
+    )?;
+
+    let mut compressed = Vec::with_capacity(lzma.len() - 4);
+    compressed.extend_from_slice(&[9, 20, 5, 0]);
+    compressed.extend_from_slice(&lzma[0..5]);
+    compressed.extend_from_slice(&lzma[13..]);
+    Ok(compressed)
+}
+
+/// Write a zip file in the layout used by the source engine, without data descriptors or extra fields
+fn write_zip(files: &[RawFile]) -> Vec<u8> {
+    fn write_u16(out: &mut Vec<u8>, value: u16) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    fn write_u32(out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let mut out = Vec::with_capacity(files.iter().map(|file| file.data.len() + 128).sum());
+    let mut offsets = Vec::with_capacity(files.len());
+
+    for file in files {
+        offsets.push(out.len() as u32);
+        write_u32(&mut out, 0x04034b50);
+        write_u16(&mut out, file.version_needed());
+        write_u16(&mut out, 0); // flags
+        write_u16(&mut out, file.compression.method());
+        write_u16(&mut out, 0); // modification time
+        write_u16(&mut out, 0); // modification date
+        write_u32(&mut out, file.crc32);
+        write_u32(&mut out, file.data.len() as u32);
+        write_u32(&mut out, file.size);
+        write_u16(&mut out, file.name.len() as u16);
+        write_u16(&mut out, 0); // extra field length
+        out.extend_from_slice(file.name.as_bytes());
+        out.extend_from_slice(&file.data);
+    }
+
+    let directory_start = out.len() as u32;
+    for (file, offset) in files.iter().zip(offsets) {
+        write_u32(&mut out, 0x02014b50);
+        write_u16(&mut out, 20); // version made by
+        write_u16(&mut out, file.version_needed());
+        write_u16(&mut out, 0); // flags
+        write_u16(&mut out, file.compression.method());
+        write_u16(&mut out, 0); // modification time
+        write_u16(&mut out, 0); // modification date
+        write_u32(&mut out, file.crc32);
+        write_u32(&mut out, file.data.len() as u32);
+        write_u32(&mut out, file.size);
+        write_u16(&mut out, file.name.len() as u16);
+        write_u16(&mut out, 0); // extra field length
+        write_u16(&mut out, 0); // comment length
+        write_u16(&mut out, 0); // disk number
+        write_u16(&mut out, 0); // internal attributes
+        write_u32(&mut out, 0); // external attributes
+        write_u32(&mut out, offset);
+        out.extend_from_slice(file.name.as_bytes());
+    }
+    let directory_size = out.len() as u32 - directory_start;
+
+    write_u32(&mut out, 0x06054b50);
+    write_u16(&mut out, 0); // disk number
+    write_u16(&mut out, 0); // disk with the central directory
+    write_u16(&mut out, files.len() as u16);
+    write_u16(&mut out, files.len() as u16);
+    write_u32(&mut out, directory_size);
+    write_u32(&mut out, directory_start);
+    write_u16(&mut out, 0); // comment length
+
+    out
+}
+
+#[test]
+fn test_packfile_modify() {
+    let mut pack = Packfile::read(Cow::Owned(write_zip(&[]))).unwrap();
+    let text = b"\"LightmappedGeneric\" { \"$basetexture\" \"foo/bar\" }".repeat(8);
+
+    pack.insert("materials/foo.vmt", &text, PackfileCompression::Lzma)
+        .unwrap();
+    pack.insert("materials/bar.txt", b"bar", PackfileCompression::Stored)
+        .unwrap();
+    pack.insert("materials/bar.txt", b"baz", PackfileCompression::Stored)
+        .unwrap();
+    assert_eq!(Some(text), pack.get("materials/foo.vmt").unwrap());
+    assert_eq!(
+        Some(b"baz".to_vec()),
+        pack.get("materials/bar.txt").unwrap()
+    );
+
+    let entries: Vec<_> = pack.entries().collect();
+    assert_eq!(2, entries.len());
+    assert_eq!(PackfileCompression::Lzma, entries[0].compression);
+    assert_eq!(PackfileCompression::Stored, entries[1].compression);
+
+    pack.insert(
+        "materials/Maps/Foo/C0_0_0.vtf",
+        b"cubemap",
+        PackfileCompression::Stored,
+    )
+    .unwrap();
+    assert!(!pack.has("materials/maps/foo/c0_0_0.vtf").unwrap());
+    assert!(pack
+        .has_ignore_case("materials/maps/foo/c0_0_0.vtf")
+        .unwrap());
+    assert_eq!(
+        Some(b"cubemap".to_vec()),
+        pack.get_ignore_case("MATERIALS/maps/foo/c0_0_0.VTF")
+            .unwrap()
+    );
+    assert!(pack.remove("materials/Maps/Foo/C0_0_0.vtf").unwrap());
+
+    assert!(pack.remove("materials\\bar.txt").unwrap());
+    assert!(!pack.remove("materials/bar.txt").unwrap());
+    assert!(!pack.has("materials/bar.txt").unwrap());
+
+    let reread = Packfile::read(Cow::Owned(pack.to_bytes())).unwrap();
+    assert!(reread.has("materials/foo.vmt").unwrap());
+}
+
+#[test]
+fn test_packfile_extract() {
+    let mut pack = Packfile::read(Cow::Owned(write_zip(&[]))).unwrap();
+    pack.insert("materials/foo.vmt", b"foo", PackfileCompression::Lzma)
+        .unwrap();
+    pack.insert("materials/bar.txt", b"bar", PackfileCompression::Stored)
+        .unwrap();
+
+    let target = std::env::temp_dir().join(format!("vbsp-extract-{}", std::process::id()));
+    assert_eq!(1, pack.extract_matching_to(&target, "**.vmt").unwrap());
+    assert_eq!(
+        b"foo".to_vec(),
+        std::fs::read(target.join("materials/foo.vmt")).unwrap()
+    );
+    assert!(!target.join("materials/bar.txt").exists());
+    assert_eq!(2, pack.extract_to(&target).unwrap());
+    std::fs::remove_dir_all(&target).unwrap();
+}
+
+/// Convert the name of a packfile entry into a relative path that can't escape the target directory
+fn safe_relative_path(name: &str) -> BspResult<PathBuf> {
+    let mut path = PathBuf::new();
+    for (i, segment) in name.split(['/', '\\']).enumerate() {
+        match segment {
+            "" if i == 0 => return Err(BspError::UnsafePackfilePath(name.into())),
+            "" | "." => {}
+            ".." => return Err(BspError::UnsafePackfilePath(name.into())),
+            segment if segment.contains(':') => {
+                return Err(BspError::UnsafePackfilePath(name.into()));
+            }
+            segment => path.push(segment),
+        }
+    }
+    if path.as_os_str().is_empty() {
+        return Err(BspError::UnsafePackfilePath(name.into()));
+    }
+    Ok(path)
+}
+
+#[test]
+fn test_safe_relative_path() {
+    assert_eq!(
+        PathBuf::from("materials/maps/foo.vtf"),
+        safe_relative_path("materials/maps/foo.vtf").unwrap()
+    );
+    assert_eq!(
+        PathBuf::from("materials/foo.vtf"),
+        safe_relative_path("materials\\./foo.vtf").unwrap()
+    );
+    assert!(safe_relative_path("../foo.vtf").is_err());
+    assert!(safe_relative_path("materials/../../foo.vtf").is_err());
+    assert!(safe_relative_path("/etc/passwd").is_err());
+    assert!(safe_relative_path("c:/windows/foo.dll").is_err());
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    // the pattern position after the last `*` and the name position it matched up to
+    let mut star = None;
+    // the same for the last `**`, and whether it's `**/` which only matches whole segments
+    let mut globstar = None;
+
+    while p < pattern.len() || n < name.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                b'*' if pattern.get(p + 1) == Some(&b'*') => {
+                    let segments = pattern.get(p + 2) == Some(&b'/');
+                    p += if segments { 3 } else { 2 };
+                    globstar = Some((p, n, segments));
+                    star = None;
+                    continue;
+                }
+                b'*' => {
+                    p += 1;
+                    star = Some((p, n));
+                    continue;
+                }
+                b'?' if n < name.len() && name[n] != b'/' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                c if n < name.len() && c.eq_ignore_ascii_case(&name[n]) => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        // let the last wildcard match one more character, `*` can't match a `/`
+        if let Some((star_p, star_n)) = star {
+            if star_n < name.len() && name[star_n] != b'/' {
+                star = Some((star_p, star_n + 1));
+                (p, n) = (star_p, star_n + 1);
+                continue;
+            }
+        }
+        // otherwise let the last `**` match one more character or segment
+        if let Some((globstar_p, globstar_n, segments)) = globstar {
+            let next = if segments {
+                name[globstar_n..]
+                    .iter()
+                    .position(|c| *c == b'/')
+                    .map(|index| globstar_n + index + 1)
+            } else {
+                (globstar_n < name.len()).then_some(globstar_n + 1)
+            };
+            if let Some(next) = next {
+                globstar = Some((globstar_p, next, segments));
+                star = None;
+                (p, n) = (globstar_p, next);
+                continue;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("materials/**", "materials/maps/foo/c0_0_0.vtf"));
+    assert!(glob_match(
+        "materials/**/*.vtf",
+        "materials/maps/foo/c0_0_0.vtf"
+    ));
+    assert!(glob_match("materials/**/*.vtf", "materials/c0_0_0.vtf"));
+    assert!(glob_match("MATERIALS/*.VMT", "materials/foo.vmt"));
+    assert!(glob_match("sp_?.vhv", "sp_1.vhv"));
+    assert!(!glob_match("sp_?.vhv", "sp_10.vhv"));
+    assert!(!glob_match("materials/*.vtf", "materials/maps/foo.vtf"));
+    assert!(!glob_match("materials/**", "models/foo.mdl"));
+    assert!(glob_match("materials/**/foo.vmt", "materials/a/b/foo.vmt"));
+    assert!(glob_match("materials/**/foo.vmt", "materials/foo.vmt"));
+    assert!(!glob_match("materials/**/foo.vmt", "materials/xfoo.vmt"));
+    assert!(!glob_match("materials/**/foo.vmt", "materials/a/xfoo.vmt"));
+    assert!(glob_match("**.vmt", "materials/a/foo.vmt"));
+    assert!(glob_match("*_*_*.vtf", "c0_0_0.vtf"));
+
+    // many wildcards don't backtrack exponentially on long names
+    let name = "a".repeat(200);
+    assert!(!glob_match("*a*a*a*a*a*a*a*a*a*a*b", &name));
+    assert!(!glob_match("**a**a**a**a**a**a**a**a**b", &name));
+}
@@ -0,0 +1,574 @@
+use super::LumpArgs;
+use crate::{BspResult, Vector};
+use binrw::{BinRead, BinReaderExt, BinResult, Endian};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::mem::size_of;
+use vdf_reader::entry::{Entry, Table};
+use vdf_reader::VdfError;
+
+const VPHYSICS_ID: [u8; 4] = *b"VPHY";
+const IVP_COMPACT_SURFACE_ID: [u8; 4] = *b"IVPS";
+const METERS_TO_INCHES: f32 = 1.0 / 0.0254;
+
+/// The physics collision data for all models in the bsp
+#[derive(Debug, Clone, Default)]
+pub struct PhysCollideLump {
+    pub models: Vec<PhysCollideModel>,
+}
+
+impl BinRead for PhysCollideLump {
+    type Args<'a> = LumpArgs;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let start = reader.stream_position()?;
+        let end = start + args.length as u64;
+        let mut models = Vec::new();
+
+        while reader.stream_position()? + size_of::<PhysModelHeader>() as u64 <= end {
+            let header = PhysModelHeader::read_options(reader, endian, ())?;
+            if header.model_index == -1 {
+                break;
+            }
+            let data_start = reader.stream_position()?;
+            let data_end = checked_end(data_start, header.data_size, end)?;
+            let solid_data = (0..header.solid_count)
+                .map(|_| {
+                    let size = i32::read_options(reader, endian, ())?;
+                    read_sized(reader, size, data_end)
+                })
+                .collect::<BinResult<Vec<_>>>()?;
+            reader.seek(SeekFrom::Start(data_end))?;
+
+            let key_data = read_sized(reader, header.key_data_size, end)?;
+            let key_data = String::from_utf8_lossy(&key_data);
+
+            models.push(PhysCollideModel {
+                model_index: header.model_index,
+                solid_data,
+                key_data: key_data.trim_end_matches('\0').into(),
+            });
+        }
+
+        Ok(PhysCollideLump { models })
+    }
+}
+
+/// Get the end of a block of `size` bytes starting at `start`, if it fits before `end`
+fn checked_end(start: u64, size: i32, end: u64) -> BinResult<u64> {
+    u64::try_from(size)
+        .ok()
+        .map(|size| start + size)
+        .filter(|block_end| *block_end <= end)
+        .ok_or_else(|| binrw::Error::AssertFail {
+            pos: start,
+            message: format!("block size {size} exceeds the lump"),
+        })
+}
+
+/// Read a block of `size` bytes, checking the size against the end of the lump before allocating
+fn read_sized<R: Read + Seek>(reader: &mut R, size: i32, end: u64) -> BinResult<Vec<u8>> {
+    let start = reader.stream_position()?;
+    let block_end = checked_end(start, size, end)?;
+    let mut data = vec![0; (block_end - start) as usize];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+#[derive(Debug, Clone, BinRead)]
+struct PhysModelHeader {
+    model_index: i32,
+    data_size: i32,
+    key_data_size: i32,
+    solid_count: i32,
+}
+
+/// The collision data for a single model
+#[derive(Debug, Clone)]
+pub struct PhysCollideModel {
+    /// Index of the model in the bsp this collision data belongs to
+    pub model_index: i32,
+    /// The serialized collision data for each solid, use [`PhysCollideModel::solids`] to decode them
+    pub solid_data: Vec<Vec<u8>>,
+    /// Text key values describing the physics properties of the solids
+    pub key_data: String,
+}
+
+impl PhysCollideModel {
+    /// Decode the collision solids of the model
+    ///
+    /// Only compact surfaces (model type 0) can be decoded, other model types return an error
+    pub fn solids(&self) -> impl Iterator<Item = BspResult<CollisionSolid>> + '_ {
+        self.solid_data
+            .iter()
+            .map(|data| CollisionSolid::read_bytes(data))
+    }
+
+    /// Decode a single collision solid by its index in the model
+    pub fn solid(&self, index: usize) -> Option<BspResult<CollisionSolid>> {
+        self.solid_data
+            .get(index)
+            .map(|data| CollisionSolid::read_bytes(data))
+    }
+
+    /// Parse the physics properties of the solids from the key data
+    pub fn properties(&self) -> Result<PhysModelProperties, VdfError> {
+        let table = Table::load_from_str(&self.key_data)?;
+        let solids = table
+            .get("solid")
+            .and_then(Entry::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Entry::as_table)
+            .map(|solid| {
+                let value = |key: &str| solid.get(key).and_then(Entry::as_str);
+                PhysSolidProperties {
+                    index: value("index")
+                        .and_then(|index| index.parse().ok())
+                        .unwrap_or_default(),
+                    surface_prop: value("surfaceprop").unwrap_or("default").into(),
+                    mass: value("mass").and_then(|mass| mass.parse().ok()),
+                }
+            })
+            .collect();
+        let material_table = table
+            .get("materialtable")
+            .and_then(Entry::as_table)
+            .map(|materials| {
+                materials
+                    .iter()
+                    .filter_map(|(index, name)| Some((index.parse().ok()?, name.as_str()?.into())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(PhysModelProperties {
+            solids,
+            material_table,
+        })
+    }
+}
+
+/// Physics properties for the solids of a model
+#[derive(Debug, Clone, Default)]
+pub struct PhysModelProperties {
+    pub solids: Vec<PhysSolidProperties>,
+    /// Surface properties referenced by the material index of collision triangles
+    pub material_table: HashMap<u8, String>,
+}
+
+impl PhysModelProperties {
+    /// Get the properties for a solid by its index in the model
+    pub fn solid(&self, index: usize) -> Option<&PhysSolidProperties> {
+        self.solids.iter().find(|solid| solid.index == index)
+    }
+
+    /// Get the name of the surface property used by a triangle of a solid
+    ///
+    /// Triangles without an entry in the material table use the surface property of the solid
+    pub fn surface_prop(&self, solid: usize, triangle: &CollisionTriangle) -> Option<&str> {
+        self.material_table
+            .get(&triangle.material_index)
+            .or_else(|| self.solid(solid).map(|solid| &solid.surface_prop))
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PhysSolidProperties {
+    /// Index of the solid in the model
+    pub index: usize,
+    /// Name of the surface property, as defined in `scripts/surfaceproperties.txt`
+    pub surface_prop: String,
+    pub mass: Option<f32>,
+}
+
+/// A single collision solid, made up from one or more convex pieces
+#[derive(Debug, Clone)]
+pub struct CollisionSolid {
+    pub convexes: Vec<CollisionConvex>,
+}
+
+impl CollisionSolid {
+    /// Read a solid from serialized vphysics collision data
+    pub fn read_bytes(data: &[u8]) -> BspResult<Self> {
+        Ok(Self::read(&mut Cursor::new(data), Endian::Little)?)
+    }
+
+    fn read<R: Read + Seek>(reader: &mut R, endian: Endian) -> BinResult<Self> {
+        let start = reader.stream_position()?;
+        let id = <[u8; 4]>::read_options(reader, endian, ())?;
+        let surface_start = if id == VPHYSICS_ID {
+            reader.seek(SeekFrom::Start(start))?;
+            let header = VPhysicsHeader::read_options(reader, endian, ())?;
+            if header.model_type != 0 {
+                return Err(binrw::Error::AssertFail {
+                    pos: start,
+                    message: format!("unsupported collision model type {}", header.model_type),
+                });
+            }
+            reader.stream_position()?
+        } else {
+            // older files store the compact surface without a header
+            start
+        };
+
+        reader.seek(SeekFrom::Start(surface_start))?;
+        let surface = CompactSurfaceHeader::read_options(reader, endian, ())?;
+
+        // child offsets are only checked to be positive, so malformed data can make nodes share
+        // children, limit the nodes visited to how many fit in the data
+        let mut node_budget = reader.seek(SeekFrom::End(0))? / size_of::<LedgeTreeNode>() as u64;
+        let mut convexes = Vec::new();
+        let mut nodes = vec![surface_start as i64 + surface.offset_ledge_tree_root as i64];
+        while let Some(node_position) = nodes.pop() {
+            if node_budget == 0 {
+                return Err(binrw::Error::AssertFail {
+                    pos: node_position as u64,
+                    message: "too many ledge tree nodes".into(),
+                });
+            }
+            node_budget -= 1;
+            reader.seek(SeekFrom::Start(node_position as u64))?;
+            let node = LedgeTreeNode::read_options(reader, endian, ())?;
+            if node.offset_right_node == 0 {
+                convexes.push(CollisionConvex::read(
+                    reader,
+                    endian,
+                    node_position + node.offset_compact_ledge as i64,
+                )?);
+            } else if node.offset_right_node > 0 {
+                nodes.push(node_position + node.offset_right_node as i64);
+                nodes.push(node_position + size_of::<LedgeTreeNode>() as i64);
+            } else {
+                return Err(binrw::Error::AssertFail {
+                    pos: node_position as u64,
+                    message: "invalid ledge tree node offset".into(),
+                });
+            }
+        }
+
+        Ok(CollisionSolid { convexes })
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(magic = b"VPHY")]
+struct VPhysicsHeader {
+    _version: i16,
+    model_type: i16,
+    _surface_size: i32,
+    _drag_axis_areas: Vector,
+    _axis_map_size: i32,
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(assert(_id == IVP_COMPACT_SURFACE_ID, "invalid compact surface id {:?}", _id))]
+struct CompactSurfaceHeader {
+    _mass_center: [f32; 3],
+    _rotation_inertia: [f32; 3],
+    _upper_limit_radius: f32,
+    _max_deviation_and_byte_size: u32,
+    offset_ledge_tree_root: i32,
+    _dummy: [i32; 2],
+    _id: [u8; 4],
+}
+
+static_assertions::const_assert_eq!(size_of::<CompactSurfaceHeader>(), 48);
+
+#[derive(Debug, Clone, BinRead)]
+struct LedgeTreeNode {
+    offset_right_node: i32,
+    offset_compact_ledge: i32,
+    _center: [f32; 3],
+    _radius: f32,
+    _box_sizes: [u8; 3],
+    _free: u8,
+}
+
+static_assertions::const_assert_eq!(size_of::<LedgeTreeNode>(), 28);
+
+#[derive(Debug, Clone, BinRead)]
+struct CompactLedgeHeader {
+    point_offset: i32,
+    client_data: i32,
+    _flags_and_size: u32,
+    triangle_count: i16,
+    _reserved: i16,
+}
+
+#[derive(Debug, Clone, BinRead)]
+struct CompactTriangle {
+    flags: u32,
+    edges: [u32; 3],
+}
+
+#[derive(Debug, Clone, BinRead)]
+struct CompactPoint {
+    x: f32,
+    y: f32,
+    z: f32,
+    _hesse: f32,
+}
+
+/// A convex piece of a collision solid
+#[derive(Debug, Clone)]
+pub struct CollisionConvex {
+    pub vertices: Vec<Vector>,
+    pub triangles: Vec<CollisionTriangle>,
+    /// Game specific data attached to the convex
+    pub game_data: i32,
+}
+
+impl CollisionConvex {
+    fn read<R: Read + Seek>(reader: &mut R, endian: Endian, position: i64) -> BinResult<Self> {
+        reader.seek(SeekFrom::Start(position as u64))?;
+        let header = CompactLedgeHeader::read_options(reader, endian, ())?;
+        let raw_triangles = (0..header.triangle_count)
+            .map(|_| CompactTriangle::read_options(reader, endian, ()))
+            .collect::<BinResult<Vec<_>>>()?;
+
+        let mut point_indexes: Vec<u16> = raw_triangles
+            .iter()
+            .flat_map(|triangle| triangle.edges.map(|edge| edge as u16))
+            .collect();
+        point_indexes.sort_unstable();
+        point_indexes.dedup();
+
+        let vertices = point_indexes
+            .iter()
+            .map(|index| {
+                let offset = header.point_offset as i64 + *index as i64 * 16;
+                reader.seek(SeekFrom::Start((position + offset) as u64))?;
+                let point = CompactPoint::read_options(reader, endian, ())?;
+                // ivp uses meters with y pointing down
+                Ok(Vector {
+                    x: point.x * METERS_TO_INCHES,
+                    y: point.z * METERS_TO_INCHES,
+                    z: -point.y * METERS_TO_INCHES,
+                })
+            })
+            .collect::<BinResult<Vec<_>>>()?;
+
+        let triangles = raw_triangles
+            .iter()
+            .map(|triangle| CollisionTriangle {
+                vertices: triangle.edges.map(|edge| {
+                    point_indexes
+                        .binary_search(&(edge as u16))
+                        .expect("all points are in the list") as u16
+                }),
+                material_index: ((triangle.flags >> 24) & 0x7f) as u8,
+            })
+            .collect();
+
+        Ok(CollisionConvex {
+            vertices,
+            triangles,
+            game_data: header.client_data,
+        })
+    }
+
+    /// Get the vertex positions of all triangles
+    pub fn triangulate(&self) -> impl Iterator<Item = [Vector; 3]> + '_ {
+        self.triangles
+            .iter()
+            .map(|triangle| triangle.vertices.map(|index| self.vertices[index as usize]))
+    }
+}
+
+/// A triangle of a collision convex
+#[derive(Debug, Clone)]
+pub struct CollisionTriangle {
+    /// Indexes into the vertices of the convex
+    pub vertices: [u16; 3],
+    pub material_index: u8,
+}
+
+/// The physics collision data for displacements
+#[derive(Debug, Clone, Default)]
+pub struct PhysDisplacementLump {
+    /// The serialized collision data for each displacement
+    pub displacements: Vec<Vec<u8>>,
+}
+
+impl BinRead for PhysDisplacementLump {
+    type Args<'a> = LumpArgs;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        if args.length < size_of::<u16>() {
+            return Ok(PhysDisplacementLump::default());
+        }
+        let count = u16::read_options(reader, endian, ())?;
+        let sizes = (0..count)
+            .map(|_| u16::read_options(reader, endian, ()))
+            .collect::<BinResult<Vec<_>>>()?;
+        let displacements = sizes
+            .into_iter()
+            .map(|size| {
+                let mut data = vec![0; size as usize];
+                reader.read_exact(&mut data)?;
+                Ok(data)
+            })
+            .collect::<BinResult<Vec<_>>>()?;
+        Ok(PhysDisplacementLump { displacements })
+    }
+}
+
+impl PhysDisplacementLump {
+    /// Get the collision model type of a displacement's collision data
+    ///
+    /// Displacements are usually stored as "virtual meshes" (type 2) which are generated from the
+    /// displacement surface, only compact surfaces (type 0) can be read as a [`CollisionSolid`]
+    pub fn model_type(&self, displacement: usize) -> Option<i16> {
+        let data = self.displacements.get(displacement)?;
+        let mut reader = Cursor::new(data);
+        reader
+            .read_le::<VPhysicsHeader>()
+            .ok()
+            .map(|header| header.model_type)
+    }
+}
+
+#[cfg(test)]
+fn test_solid_data() -> Vec<u8> {
+    fn push(data: &mut Vec<u8>, values: &[u32]) {
+        for value in values {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let float = |f: f32| f.to_bits();
+
+    let mut data = Vec::new();
+    data.extend_from_slice(b"VPHY");
+    data.extend_from_slice(&0x100i16.to_le_bytes());
+    data.extend_from_slice(&0i16.to_le_bytes());
+    push(&mut data, &[0, 0, 0, 0, 0]);
+
+    // compact surface header, ledge tree root at offset 48
+    push(&mut data, &[0; 7]);
+    push(&mut data, &[0, 48, 0, 0]);
+    data.extend_from_slice(b"IVPS");
+    // single terminal node, ledge directly after the node
+    push(&mut data, &[0, 28, 0, 0, 0, 0, 0]);
+    // ledge with a single triangle, points after the triangle
+    push(&mut data, &[32, 0, 0, 1]);
+    push(&mut data, &[3 << 24, 0, 1, 2]);
+    push(&mut data, &[float(0.0), float(0.0), float(0.0), 0]);
+    push(&mut data, &[float(0.0254), float(0.0), float(0.0), 0]);
+    push(&mut data, &[float(0.0), float(-0.0254), float(0.0), 0]);
+    data
+}
+
+#[test]
+fn test_read_collision_solid() {
+    let solid = CollisionSolid::read_bytes(&test_solid_data()).unwrap();
+    assert_eq!(1, solid.convexes.len());
+    let convex = &solid.convexes[0];
+    assert_eq!(3, convex.triangles[0].material_index);
+    let triangle: Vec<[f32; 3]> = convex
+        .triangulate()
+        .flatten()
+        .map(|vertex| <[f32; 3]>::from(vertex).map(f32::round))
+        .collect();
+    assert_eq!(
+        vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+        triangle
+    );
+}
+
+#[test]
+fn test_phys_model_properties() {
+    let model = PhysCollideModel {
+        model_index: 0,
+        solid_data: Vec::new(),
+        key_data: r#"solid
+{
+"index" "0"
+"mass" "5000.000000"
+"surfaceprop" "metal"
+}
+solid
+{
+"index" "1"
+"surfaceprop" "wood"
+}
+materialtable
+{
+"1" "dirt"
+}
+"#
+        .into(),
+    };
+    let properties = model.properties().unwrap();
+    assert_eq!(2, properties.solids.len());
+    assert_eq!(Some(5000.0), properties.solid(0).unwrap().mass);
+
+    let triangle = |material_index| CollisionTriangle {
+        vertices: [0, 1, 2],
+        material_index,
+    };
+    assert_eq!(Some("metal"), properties.surface_prop(0, &triangle(0)));
+    assert_eq!(Some("wood"), properties.surface_prop(1, &triangle(0)));
+    assert_eq!(Some("dirt"), properties.surface_prop(1, &triangle(1)));
+    assert_eq!(None, properties.surface_prop(2, &triangle(0)));
+}
+
+#[test]
+fn test_read_phys_collide_lump() {
+    fn model_data(solids: &[&[u8]], data_size: Option<i32>, key_data: &[u8]) -> Vec<u8> {
+        let solid_size: usize = solids.iter().map(|solid| solid.len() + 4).sum();
+        let mut data = Vec::new();
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&data_size.unwrap_or(solid_size as i32).to_le_bytes());
+        data.extend_from_slice(&(key_data.len() as i32).to_le_bytes());
+        data.extend_from_slice(&(solids.len() as i32).to_le_bytes());
+        for solid in solids {
+            data.extend_from_slice(&(solid.len() as i32).to_le_bytes());
+            data.extend_from_slice(solid);
+        }
+        data.extend_from_slice(key_data);
+        data
+    }
+    let read = |data: &[u8]| {
+        PhysCollideLump::read_options(
+            &mut Cursor::new(data),
+            Endian::Little,
+            LumpArgs {
+                length: data.len(),
+                ..Default::default()
+            },
+        )
+    };
+
+    // unsupported and malformed solids are kept as raw data
+    let mut virtual_mesh = test_solid_data();
+    virtual_mesh[6] = 2;
+    let solid = test_solid_data();
+    let data = model_data(
+        &[&solid, &virtual_mesh, b"junk"],
+        None,
+        b"solid { \"index\" \"0\" \"surfaceprop\" \"metal\xff\" }\0",
+    );
+    let lump = read(&data).unwrap();
+    let model = &lump.models[0];
+    assert_eq!(3, model.solid_data.len());
+    assert_eq!(1, model.solid(0).unwrap().unwrap().convexes.len());
+    assert!(model.solid(1).unwrap().is_err());
+    assert!(model.solid(2).unwrap().is_err());
+    assert!(model.key_data.contains("metal\u{fffd}"));
+
+    // sizes larger than the lump are rejected without allocating them
+    let data = model_data(&[], Some(i32::MAX), b"");
+    assert!(read(&data).is_err());
+    let mut data = model_data(&[b"data"], None, b"");
+    data[16..20].copy_from_slice(&i32::MAX.to_le_bytes());
+    assert!(read(&data).is_err());
+}
@@ -1,4 +1,4 @@
-use crate::{Handle, StaticPropLump};
+use crate::{Handle, RawEntity, StaticPropLump};
 use vbsp_common::{AsPropPlacement, PropPlacement};
 
 impl<'a> AsPropPlacement<'a> for Handle<'a, StaticPropLump> {
@@ -12,3 +12,102 @@ impl<'a> AsPropPlacement<'a> for Handle<'a, StaticPropLump> {
         }
     }
 }
+
+/// A prop placed by an entity like `prop_dynamic`, `prop_physics` or `prop_door_rotating`
+#[derive(Debug, Clone)]
+pub struct PropEntity<'a> {
+    entity: RawEntity<'a>,
+}
+
+impl<'a> PropEntity<'a> {
+    /// Use the entity as a prop if it's a `prop_*` entity with a studio model
+    ///
+    /// Other entities with a `.mdl` model, like pickups or npcs, aren't included.
+    pub fn new(entity: RawEntity<'a>) -> Option<Self> {
+        let is_prop = entity.prop("classname").is_some_and(|class| {
+            class
+                .get(..5)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("prop_"))
+        });
+        if !is_prop {
+            return None;
+        }
+        let model = entity.prop("model")?;
+        let is_studio_model = model
+            .get(model.len().saturating_sub(4)..)
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(".mdl"));
+        is_studio_model.then_some(PropEntity { entity })
+    }
+
+    pub fn entity(&self) -> &RawEntity<'a> {
+        &self.entity
+    }
+}
+
+impl<'a> AsPropPlacement<'a> for PropEntity<'a> {
+    fn as_prop_placement(&self) -> PropPlacement<'a> {
+        let transform = self.entity.transform();
+        PropPlacement {
+            model: self.entity.prop("model").unwrap_or_default(),
+            rotation: transform.angles.as_quaternion(),
+            scale: self
+                .entity
+                .prop_parse("modelscale")
+                .and_then(Result::ok)
+                .unwrap_or(1.0),
+            origin: transform.origin,
+            skin: self
+                .entity
+                .prop_parse("skin")
+                .and_then(Result::ok)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[test]
+fn test_prop_entity_placement() {
+    use crate::{Entities, Vector};
+
+    let entities = Entities::new(
+        r#"
+{
+"classname" "prop_dynamic"
+"model" "models/props_gameplay/resupply_locker.MDL"
+"origin" "10 20 30"
+"angles" "0 90 0"
+"skin" "1"
+"modelscale" "1.5"
+}
+{
+"classname" "prop_physics"
+"model" "models/props_farm/wooden_barrel.mdl"
+}
+{
+"classname" "func_door"
+"model" "*3"
+}
+{
+"classname" "item_healthkit_full"
+"model" "models/items/medkit_large.mdl"
+}
+{
+"classname" "prop_dynamic"
+"model" "*4"
+}
+"#
+        .into(),
+    );
+    let props: Vec<_> = entities.iter().filter_map(PropEntity::new).collect();
+    assert_eq!(2, props.len());
+
+    let placement = props[0].as_prop_placement();
+    assert_eq!("models/props_gameplay/resupply_locker.MDL", placement.model);
+    assert_eq!(Vector::from([10.0, 20.0, 30.0]), placement.origin);
+    assert_eq!(1, placement.skin);
+    assert_eq!(1.5, placement.scale);
+
+    let placement = props[1].as_prop_placement();
+    assert_eq!(0, placement.skin);
+    assert_eq!(1.0, placement.scale);
+}
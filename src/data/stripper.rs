@@ -0,0 +1,484 @@
+use crate::{Entities, EntityList, EntityProperties, EntitySyntaxError};
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+/// A Stripper:Source config file, applying `filter:`, `add:` and `modify:` blocks to the map entities
+///
+/// Values wrapped in `/` are matched as case-insensitive regular expressions,
+/// other values and all keys are compared case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct StripperConfig {
+    pub actions: Vec<StripperAction>,
+}
+
+#[derive(Debug, Clone)]
+pub enum StripperAction {
+    /// Remove all entities matching the properties
+    Filter(Vec<PropertyMatch>),
+    /// Add a new entity
+    Add(EntityProperties),
+    Modify(StripperModify),
+}
+
+/// Change the properties of all entities matching `matches`
+///
+/// Changes are applied in the order replace, delete, insert.
+#[derive(Debug, Clone, Default)]
+pub struct StripperModify {
+    pub matches: Vec<PropertyMatch>,
+    /// Change the value of properties the entity already has
+    pub replace: EntityProperties,
+    /// Remove properties with a matching value
+    pub delete: Vec<PropertyMatch>,
+    /// Add new properties
+    pub insert: EntityProperties,
+}
+
+#[derive(Debug, Clone)]
+pub struct PropertyMatch {
+    pub key: String,
+    pub value: ValueMatch,
+}
+
+#[derive(Debug, Clone)]
+pub enum ValueMatch {
+    Exact(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Error)]
+pub enum StripperError {
+    #[error("syntax error on line {line}: {message}")]
+    Syntax { line: usize, message: &'static str },
+    #[error("unknown section {name:?} on line {line}")]
+    UnknownSection { line: usize, name: String },
+    #[error("invalid regex on line {line}: {error}")]
+    Regex { line: usize, error: regex::Error },
+}
+
+impl ValueMatch {
+    fn new(value: &str, line: usize) -> Result<Self, StripperError> {
+        match value
+            .strip_prefix('/')
+            .and_then(|value| value.strip_suffix('/'))
+        {
+            Some(pattern) => RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(ValueMatch::Regex)
+                .map_err(|error| StripperError::Regex { line, error }),
+            None => Ok(ValueMatch::Exact(value.into())),
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueMatch::Exact(expected) => expected.eq_ignore_ascii_case(value),
+            ValueMatch::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+impl PropertyMatch {
+    pub fn matches(&self, key: &str, value: &str) -> bool {
+        self.key.eq_ignore_ascii_case(key) && self.value.matches(value)
+    }
+
+    /// Check if any of the values of the entity for this key match
+    pub fn matches_entity(&self, entity: &EntityProperties) -> bool {
+        entity
+            .get_all(&self.key)
+            .any(|value| self.value.matches(value))
+    }
+}
+
+impl StripperConfig {
+    pub fn parse(config: &str) -> Result<Self, StripperError> {
+        let mut tokens = Tokenizer::new(config);
+        let mut actions = Vec::new();
+        let mut section = None;
+
+        while let Some(token) = tokens.next()? {
+            match token.kind {
+                TokenKind::Label(name) => {
+                    section = Some(match name.to_ascii_lowercase().as_str() {
+                        "filter" | "remove" => Section::Filter,
+                        "add" => Section::Add,
+                        "modify" => Section::Modify,
+                        _ => {
+                            return Err(StripperError::UnknownSection {
+                                line: token.line,
+                                name: name.into(),
+                            });
+                        }
+                    })
+                }
+                TokenKind::Open => {
+                    let action = match section {
+                        Some(Section::Filter) => StripperAction::Filter(read_matches(&mut tokens)?),
+                        Some(Section::Add) => StripperAction::Add(read_properties(&mut tokens)?),
+                        Some(Section::Modify) => StripperAction::Modify(read_modify(&mut tokens)?),
+                        None => {
+                            return Err(StripperError::Syntax {
+                                line: token.line,
+                                message: "expected a section label before the block",
+                            });
+                        }
+                    };
+                    actions.push(action);
+                }
+                _ => {
+                    return Err(StripperError::Syntax {
+                        line: token.line,
+                        message: "expected a section label or block",
+                    });
+                }
+            }
+        }
+
+        Ok(StripperConfig { actions })
+    }
+
+    /// Apply all actions in order
+    pub fn apply(&self, entities: &mut EntityList) {
+        for action in &self.actions {
+            action.apply(entities);
+        }
+    }
+
+    /// Apply the config to the entities of a map
+    pub fn apply_to(&self, entities: &Entities) -> Result<EntityList, EntitySyntaxError> {
+        let mut list = EntityList::try_from(entities)?;
+        self.apply(&mut list);
+        Ok(list)
+    }
+}
+
+impl StripperAction {
+    pub fn apply(&self, entities: &mut EntityList) {
+        match self {
+            StripperAction::Filter(matches) => {
+                entities.retain(|entity| !matches_all(matches, entity));
+            }
+            StripperAction::Add(entity) => entities.push(entity.clone()),
+            StripperAction::Modify(modify) => {
+                for entity in entities.iter_mut() {
+                    if matches_all(&modify.matches, entity) {
+                        modify.apply(entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl StripperModify {
+    fn apply(&self, entity: &mut EntityProperties) {
+        if !self.replace.is_empty() {
+            *entity = entity
+                .iter()
+                .map(|(key, value)| (key, self.replace.get(key).unwrap_or(value)))
+                .collect();
+        }
+        if !self.delete.is_empty() {
+            *entity = entity
+                .iter()
+                .filter(|(key, value)| !self.delete.iter().any(|delete| delete.matches(key, value)))
+                .collect();
+        }
+        for (key, value) in self.insert.iter() {
+            entity.push(key, value);
+        }
+    }
+}
+
+/// An empty list of matches matches no entity
+fn matches_all(matches: &[PropertyMatch], entity: &EntityProperties) -> bool {
+    !matches.is_empty() && matches.iter().all(|matcher| matcher.matches_entity(entity))
+}
+
+#[derive(Clone, Copy)]
+enum Section {
+    Filter,
+    Add,
+    Modify,
+}
+
+fn read_properties(tokens: &mut Tokenizer) -> Result<EntityProperties, StripperError> {
+    let mut properties = EntityProperties::new();
+    while let Some((key, value, _)) = read_property(tokens)? {
+        properties.push(key, value);
+    }
+    Ok(properties)
+}
+
+fn read_matches(tokens: &mut Tokenizer) -> Result<Vec<PropertyMatch>, StripperError> {
+    let mut matches = Vec::new();
+    while let Some((key, value, line)) = read_property(tokens)? {
+        matches.push(PropertyMatch {
+            key: key.into(),
+            value: ValueMatch::new(value, line)?,
+        });
+    }
+    Ok(matches)
+}
+
+/// Read a key-value pair, or `None` when reaching the end of the block
+fn read_property<'a>(
+    tokens: &mut Tokenizer<'a>,
+) -> Result<Option<(&'a str, &'a str, usize)>, StripperError> {
+    let key = tokens.expect()?;
+    let key = match key.kind {
+        TokenKind::Close => return Ok(None),
+        TokenKind::String(key) => key,
+        _ => {
+            return Err(StripperError::Syntax {
+                line: key.line,
+                message: "expected a key or the end of the block",
+            });
+        }
+    };
+    let value = tokens.expect()?;
+    match value.kind {
+        TokenKind::String(value_str) => Ok(Some((key, value_str, value.line))),
+        _ => Err(StripperError::Syntax {
+            line: value.line,
+            message: "expected a value",
+        }),
+    }
+}
+
+fn read_modify(tokens: &mut Tokenizer) -> Result<StripperModify, StripperError> {
+    let mut modify = StripperModify::default();
+    loop {
+        let token = tokens.expect()?;
+        let name = match token.kind {
+            TokenKind::Close => return Ok(modify),
+            TokenKind::Label(name) => name,
+            _ => {
+                return Err(StripperError::Syntax {
+                    line: token.line,
+                    message: "expected a modify section label",
+                });
+            }
+        };
+        let open = tokens.expect()?;
+        if !matches!(open.kind, TokenKind::Open) {
+            return Err(StripperError::Syntax {
+                line: open.line,
+                message: "expected a block",
+            });
+        }
+        match name.to_ascii_lowercase().as_str() {
+            "match" => modify.matches.extend(read_matches(tokens)?),
+            "replace" => modify.replace.extend(read_properties(tokens)?),
+            "delete" => modify.delete.extend(read_matches(tokens)?),
+            "insert" => modify.insert.extend(read_properties(tokens)?),
+            _ => {
+                return Err(StripperError::UnknownSection {
+                    line: token.line,
+                    name: name.into(),
+                });
+            }
+        }
+    }
+}
+
+struct Token<'a> {
+    kind: TokenKind<'a>,
+    line: usize,
+}
+
+enum TokenKind<'a> {
+    Open,
+    Close,
+    /// A section name followed by `:`
+    Label(&'a str),
+    String(&'a str),
+}
+
+struct Tokenizer<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(input: &'a str) -> Self {
+        Tokenizer {
+            rest: input,
+            line: 1,
+        }
+    }
+
+    fn advance(&mut self, count: usize) -> &'a str {
+        let (taken, rest) = self.rest.split_at(count);
+        self.line += taken.matches('\n').count();
+        self.rest = rest;
+        taken
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let trimmed = self.rest.trim_start();
+            self.advance(self.rest.len() - trimmed.len());
+            if trimmed.starts_with(';') || trimmed.starts_with('#') || trimmed.starts_with("//") {
+                self.advance(trimmed.find('\n').unwrap_or(trimmed.len()));
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn expect(&mut self) -> Result<Token<'a>, StripperError> {
+        let line = self.line;
+        self.next()?.ok_or(StripperError::Syntax {
+            line,
+            message: "unexpected end of file",
+        })
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, StripperError> {
+        self.skip_whitespace();
+        let line = self.line;
+        let kind = match self.rest.chars().next() {
+            None => return Ok(None),
+            Some('{') => {
+                self.advance(1);
+                TokenKind::Open
+            }
+            Some('}') => {
+                self.advance(1);
+                TokenKind::Close
+            }
+            Some('"') => {
+                let end = self.rest[1..]
+                    .find(['"', '\n'])
+                    .filter(|end| self.rest[1 + end..].starts_with('"'))
+                    .ok_or(StripperError::Syntax {
+                        line,
+                        message: "unterminated string",
+                    })?;
+                let string = &self.advance(end + 2)[1..end + 1];
+                TokenKind::String(string)
+            }
+            Some(_) => {
+                let end = self
+                    .rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | '"'))
+                    .unwrap_or(self.rest.len());
+                let word = self.advance(end);
+                match word.strip_suffix(':') {
+                    Some(label) => TokenKind::Label(label),
+                    None => TokenKind::String(word),
+                }
+            }
+        };
+        Ok(Some(Token { kind, line }))
+    }
+}
+
+#[test]
+fn test_stripper_config() {
+    let config = StripperConfig::parse(
+        r#"
+; remove the koth logic
+filter:
+{
+    "classname" "tf_logic_koth"
+}
+{
+    "classname" "/^item_(health|ammo)kit_small$/"
+}
+
+add:
+{
+    "classname" "info_target"
+    "targetname" "stripper_target"
+}
+
+modify:
+{
+    match:
+    {
+        "classname" "func_door"
+    }
+    replace:
+    {
+        "speed" "500"
+    }
+    delete:
+    {
+        "OnOpen" "/relay_old/"
+    }
+    insert:
+    {
+        "OnOpen" "relay_new,Trigger,,0,-1"
+    }
+}
+"#,
+    )
+    .unwrap();
+    assert_eq!(4, config.actions.len());
+
+    let entities = Entities::new(
+        r#"
+{
+"classname" "worldspawn"
+}
+{
+"classname" "tf_logic_koth"
+}
+{
+"classname" "item_healthkit_small"
+}
+{
+"classname" "item_healthkit_full"
+}
+{
+"classname" "func_door"
+"speed" "100"
+"OnOpen" "relay_old,Trigger,,0,-1"
+"OnOpen" "relay_keep,Trigger,,0,-1"
+}
+"#
+        .into(),
+    );
+    let result = config.apply_to(&entities).unwrap();
+    let classes: Vec<_> = result.iter().filter_map(|entity| entity.class()).collect();
+    assert_eq!(
+        vec![
+            "worldspawn",
+            "item_healthkit_full",
+            "func_door",
+            "info_target"
+        ],
+        classes
+    );
+
+    let door = result.by_class("func_door").next().unwrap();
+    assert_eq!(
+        vec![
+            ("classname", "func_door"),
+            ("speed", "500"),
+            ("OnOpen", "relay_keep,Trigger,,0,-1"),
+            ("OnOpen", "relay_new,Trigger,,0,-1"),
+        ],
+        door.iter().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_stripper_errors() {
+    assert!(matches!(
+        StripperConfig::parse("filter:\n{\n\"classname\"\n}"),
+        Err(StripperError::Syntax { line: 4, .. })
+    ));
+    assert!(matches!(
+        StripperConfig::parse("strip:\n{\n}"),
+        Err(StripperError::UnknownSection { line: 1, .. })
+    ));
+    assert!(matches!(
+        StripperConfig::parse("filter:\n{\n\"classname\" \"/(/\"\n}"),
+        Err(StripperError::Regex { line: 3, .. })
+    ));
+}
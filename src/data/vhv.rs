@@ -0,0 +1,142 @@
+use crate::BspResult;
+use binrw::{BinRead, BinReaderExt, BinResult, Endian};
+use bitflags::bitflags;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::mem::size_of;
+
+/// Baked per-vertex lighting for a static prop, stored in the pak as `sp_<n>.vhv` or `sp_hdr_<n>.vhv`
+#[derive(Debug, Clone)]
+pub struct VertexLighting {
+    pub header: VertexLightingHeader,
+    pub meshes: Vec<VertexLightingMesh>,
+}
+
+impl VertexLighting {
+    pub fn read(data: &[u8]) -> BspResult<Self> {
+        let mut reader = Cursor::new(data);
+        Ok(reader.read_le()?)
+    }
+
+    /// Get the meshes for a specific level of detail
+    pub fn lod(&self, lod: i32) -> impl Iterator<Item = &VertexLightingMesh> {
+        self.meshes.iter().filter(move |mesh| mesh.lod == lod)
+    }
+}
+
+impl BinRead for VertexLighting {
+    type Args<'a> = ();
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let header = VertexLightingHeader::read_options(reader, endian, args)?;
+        let mesh_headers = (0..header.mesh_count)
+            .map(|_| VertexLightingMeshHeader::read_options(reader, endian, args))
+            .collect::<BinResult<Vec<_>>>()?;
+        let meshes = mesh_headers
+            .into_iter()
+            .map(|mesh| {
+                reader.seek(SeekFrom::Start(mesh.offset as u64))?;
+                let colors = (0..mesh.vertex_count)
+                    .map(|_| {
+                        let color = VertexColor::read_options(reader, endian, args)?;
+                        reader.seek(SeekFrom::Current(
+                            header.vertex_size as i64 - size_of::<VertexColor>() as i64,
+                        ))?;
+                        Ok(color)
+                    })
+                    .collect::<BinResult<Vec<_>>>()?;
+                Ok(VertexLightingMesh {
+                    lod: mesh.lod,
+                    colors,
+                })
+            })
+            .collect::<BinResult<Vec<_>>>()?;
+        Ok(VertexLighting { header, meshes })
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(assert(version == 2), assert(vertex_size as usize >= size_of::<VertexColor>()))]
+pub struct VertexLightingHeader {
+    pub version: i32,
+    /// Checksum of the model the lighting was calculated for
+    pub checksum: u32,
+    pub vertex_flags: VertexLightingFlags,
+    pub vertex_size: u32,
+    pub vertex_count: u32,
+    pub mesh_count: i32,
+    pub unused: [u32; 4],
+}
+
+static_assertions::const_assert_eq!(size_of::<VertexLightingHeader>(), 40);
+
+#[derive(BinRead, Debug, Clone, Copy)]
+pub struct VertexLightingFlags(u32);
+
+bitflags! {
+    impl VertexLightingFlags: u32 {
+        const POSITION = 0x1;
+        const NORMAL = 0x2;
+        const COLOR = 0x4;
+        const SPECULAR = 0x8;
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+struct VertexLightingMeshHeader {
+    lod: i32,
+    vertex_count: u32,
+    offset: u32,
+    _unused: [u32; 4],
+}
+
+static_assertions::const_assert_eq!(size_of::<VertexLightingMeshHeader>(), 28);
+
+/// The lit vertex colors for a single mesh of a model
+#[derive(Debug, Clone)]
+pub struct VertexLightingMesh {
+    pub lod: i32,
+    pub colors: Vec<VertexColor>,
+}
+
+/// Vertex color, stored as BGRA
+#[derive(Debug, Clone, Copy, Default, BinRead)]
+pub struct VertexColor {
+    pub b: u8,
+    pub g: u8,
+    pub r: u8,
+    pub a: u8,
+}
+
+impl VertexColor {
+    pub fn rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+#[test]
+fn test_read_vertex_lighting() {
+    let mut data = Vec::new();
+    for value in [2, 0x1234, 0x4, 4, 3, 2, 0, 0, 0, 0] {
+        data.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    for value in [0, 2, 96, 0, 0, 0, 0, 1, 1, 104, 0, 0, 0, 0] {
+        data.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+
+    let lighting = VertexLighting::read(&data).unwrap();
+    assert_eq!(lighting.header.checksum, 0x1234);
+    assert_eq!(lighting.meshes.len(), 2);
+    assert_eq!(
+        vec![[3, 2, 1, 4], [7, 6, 5, 8]],
+        lighting
+            .lod(0)
+            .flat_map(|mesh| mesh.colors.iter().map(VertexColor::rgba))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!([11, 10, 9, 12], lighting.meshes[1].colors[0].rgba());
+}
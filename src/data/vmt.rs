@@ -0,0 +1,359 @@
+use crate::BspResult;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Maximum number of nested `patch` materials to follow
+const MAX_PATCH_DEPTH: usize = 8;
+
+/// A material definition as stored in a `.vmt` file
+#[derive(Debug, Clone, Default)]
+pub struct Vmt {
+    /// The shader of the material in lowercase
+    pub shader: String,
+    /// The parameters of the material, with keys in lowercase
+    pub parameters: HashMap<String, String>,
+    /// The patch to apply, for materials using the `patch` shader
+    pub patch: Option<VmtPatch>,
+}
+
+/// Changes a `patch` material applies to the material it includes
+#[derive(Debug, Clone, Default)]
+pub struct VmtPatch {
+    pub include: String,
+    /// Parameters to change, only if they are set in the included material
+    pub replace: HashMap<String, String>,
+    /// Parameters to set, overwriting any existing value
+    pub insert: HashMap<String, String>,
+}
+
+#[derive(Debug, Error)]
+pub enum VmtError {
+    #[error("Malformed material: {0}")]
+    Syntax(&'static str),
+    #[error("Material doesn't contain a shader")]
+    NoShader,
+    #[error("Patch material doesn't specify a material to include")]
+    NoPatchInclude,
+    #[error("Patch materials nested more than {MAX_PATCH_DEPTH} levels deep")]
+    PatchDepth,
+}
+
+impl Vmt {
+    pub fn parse(text: &str) -> Result<Self, VmtError> {
+        let root = parse_key_values(text)?;
+        let (shader, body) = root
+            .into_iter()
+            .find_map(|(shader, body)| match body {
+                KeyValue::Table(body) => Some((shader.to_ascii_lowercase(), body)),
+                KeyValue::Value(_) => None,
+            })
+            .ok_or(VmtError::NoShader)?;
+
+        if shader == "patch" {
+            let mut patch = VmtPatch::default();
+            let mut include = None;
+            for (key, value) in body {
+                match (key.to_ascii_lowercase().as_str(), value) {
+                    ("include", KeyValue::Value(value)) => include = Some(value),
+                    ("replace", KeyValue::Table(table)) => patch.replace.extend(parameters(table)),
+                    ("insert", KeyValue::Table(table)) => patch.insert.extend(parameters(table)),
+                    _ => {}
+                }
+            }
+            patch.include = include.ok_or(VmtError::NoPatchInclude)?;
+            Ok(Vmt {
+                shader,
+                parameters: HashMap::new(),
+                patch: Some(patch),
+            })
+        } else {
+            Ok(Vmt {
+                shader,
+                parameters: parameters(body),
+                patch: None,
+            })
+        }
+    }
+
+    /// Resolve `patch` materials by loading the included material and applying the patch
+    ///
+    /// `load` is called with the path of the included material and should return the contents of the file.
+    /// If an included material can't be found, the patch material is returned unresolved.
+    pub fn resolve(
+        self,
+        mut load: impl FnMut(&str) -> BspResult<Option<Vec<u8>>>,
+    ) -> BspResult<Vmt> {
+        let mut vmt = self.clone();
+        let mut patches = Vec::new();
+        while let Some(patch) = vmt.patch.take() {
+            if patches.len() >= MAX_PATCH_DEPTH {
+                return Err(VmtError::PatchDepth.into());
+            }
+            let path = normalize_material_path(&patch.include);
+            let Some(data) = load(&path)? else {
+                return Ok(self);
+            };
+            vmt = Vmt::parse(&String::from_utf8_lossy(&data))?;
+            patches.push(patch);
+        }
+
+        for patch in patches.into_iter().rev() {
+            for (key, value) in patch.replace {
+                if let Some(existing) = vmt.parameters.get_mut(&key) {
+                    *existing = value;
+                }
+            }
+            vmt.parameters.extend(patch.insert);
+        }
+        Ok(vmt)
+    }
+
+    /// Get a material parameter, the key is case-insensitive
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.parameters
+            .get(&key.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.get(key)
+            .and_then(|value| value.trim().parse::<f32>().ok())
+            .is_some_and(|value| value != 0.0)
+    }
+
+    pub fn base_texture(&self) -> Option<&str> {
+        self.get("$basetexture")
+    }
+
+    pub fn base_texture2(&self) -> Option<&str> {
+        self.get("$basetexture2")
+    }
+
+    pub fn bump_map(&self) -> Option<&str> {
+        self.get("$bumpmap").or_else(|| self.get("$normalmap"))
+    }
+
+    pub fn surface_prop(&self) -> Option<&str> {
+        self.get("$surfaceprop")
+    }
+
+    pub fn env_map(&self) -> Option<&str> {
+        self.get("$envmap")
+    }
+
+    pub fn translucent(&self) -> bool {
+        self.flag("$translucent")
+    }
+
+    pub fn alpha_test(&self) -> bool {
+        self.flag("$alphatest")
+    }
+
+    pub fn additive(&self) -> bool {
+        self.flag("$additive")
+    }
+
+    pub fn no_cull(&self) -> bool {
+        self.flag("$nocull")
+    }
+
+    /// Get the alpha value for the material, `1.0` if not set
+    pub fn alpha(&self) -> f32 {
+        self.get("$alpha")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(1.0)
+    }
+}
+
+/// Get the path of the `.vmt` file in the game filesystem for a material name
+pub fn material_path(name: &str) -> String {
+    normalize_material_path(&format!("materials/{name}.vmt"))
+}
+
+fn normalize_material_path(path: &str) -> String {
+    let path = path.replace('\\', "/").to_ascii_lowercase();
+    if path.ends_with(".vmt") {
+        path
+    } else {
+        format!("{path}.vmt")
+    }
+}
+
+fn parameters(table: Vec<(String, KeyValue)>) -> HashMap<String, String> {
+    table
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            KeyValue::Value(value) => Some((key.to_ascii_lowercase(), value)),
+            KeyValue::Table(_) => None,
+        })
+        .collect()
+}
+
+enum KeyValue {
+    Value(String),
+    Table(Vec<(String, KeyValue)>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    String(&'a str),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = Result<Token<'_>, VmtError>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        loop {
+            rest = rest.trim_start();
+            if rest.starts_with("//") {
+                rest = rest.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+            } else {
+                break;
+            }
+        }
+        let token = match rest.chars().next()? {
+            '{' => {
+                rest = &rest[1..];
+                Token::Open
+            }
+            '}' => {
+                rest = &rest[1..];
+                Token::Close
+            }
+            '"' => match rest[1..].split_once('"') {
+                Some((value, remaining)) => {
+                    rest = remaining;
+                    Token::String(value)
+                }
+                None => return Some(Err(VmtError::Syntax("unterminated string"))),
+            },
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | '"'))
+                    .unwrap_or(rest.len());
+                let (value, remaining) = rest.split_at(end);
+                rest = remaining;
+                Token::String(value)
+            }
+        };
+        Some(Ok(token))
+    })
+}
+
+fn parse_key_values(text: &str) -> Result<Vec<(String, KeyValue)>, VmtError> {
+    // platform conditionals like `[$WIN32]` are ignored
+    let mut tokens = tokenize(text)
+        .filter(|token| !matches!(token, Ok(Token::String(value)) if value.starts_with("[$") || value.starts_with("[!$")))
+        .peekable();
+    let mut stack: Vec<Vec<(String, KeyValue)>> = vec![Vec::new()];
+    let mut keys: Vec<String> = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token? {
+            Token::String(key) => match tokens.next().transpose()? {
+                Some(Token::String(value)) => stack
+                    .last_mut()
+                    .unwrap()
+                    .push((key.into(), KeyValue::Value(value.into()))),
+                Some(Token::Open) => {
+                    keys.push(key.into());
+                    stack.push(Vec::new());
+                }
+                _ => return Err(VmtError::Syntax("expected value after key")),
+            },
+            Token::Close => {
+                let (Some(key), Some(table)) = (keys.pop(), stack.pop()) else {
+                    return Err(VmtError::Syntax("unexpected closing brace"));
+                };
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .push((key, KeyValue::Table(table)));
+            }
+            Token::Open => return Err(VmtError::Syntax("unexpected opening brace")),
+        }
+    }
+
+    match (stack.pop(), stack.is_empty()) {
+        (Some(root), true) => Ok(root),
+        _ => Err(VmtError::Syntax("unclosed brace")),
+    }
+}
+
+#[test]
+fn test_parse_vmt() {
+    let vmt = Vmt::parse(
+        r#"
+        "LightmappedGeneric"
+        {
+            // comment
+            "$baseTexture" "Concrete/Wall01"
+            $surfaceprop concrete
+            "$color" "[1 0.5 0.5]"
+            "$envmap" "env_cubemap" [$WIN32]
+            "$translucent" 1
+            "Proxies"
+            {
+                "AnimatedTexture" {}
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!("lightmappedgeneric", vmt.shader);
+    assert_eq!(Some("Concrete/Wall01"), vmt.base_texture());
+    assert_eq!(Some("concrete"), vmt.surface_prop());
+    assert_eq!(Some("[1 0.5 0.5]"), vmt.get("$COLOR"));
+    assert_eq!(Some("env_cubemap"), vmt.env_map());
+    assert_eq!(None, vmt.bump_map());
+    assert!(vmt.translucent());
+    assert!(!vmt.alpha_test());
+}
+
+#[test]
+fn test_resolve_patch_vmt() {
+    let patch = Vmt::parse(
+        r#"
+        "patch"
+        {
+            "include" "materials\concrete\wall01.vmt"
+            "replace"
+            {
+                "$envmap" "maps/foo/c0_0_0"
+                "$detail" "foo"
+            }
+            "insert"
+            {
+                "$bumpmap" "concrete/wall01_normal"
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    let vmt = patch
+        .resolve(|path| {
+            assert_eq!("materials/concrete/wall01.vmt", path);
+            Ok(Some(
+                br#""LightmappedGeneric" { "$basetexture" "concrete/wall01" "$envmap" "env_cubemap" }"#
+                    .to_vec(),
+            ))
+        })
+        .unwrap();
+
+    assert_eq!("lightmappedgeneric", vmt.shader);
+    assert_eq!(Some("concrete/wall01"), vmt.base_texture());
+    assert_eq!(Some("maps/foo/c0_0_0"), vmt.env_map());
+    assert_eq!(Some("concrete/wall01_normal"), vmt.bump_map());
+    assert_eq!(None, vmt.get("$detail"));
+}
+
+#[test]
+fn test_resolve_patch_vmt_missing_include() {
+    let patch =
+        Vmt::parse(r#""patch" { "include" "materials/missing.vmt" "insert" { "$detail" "foo" } }"#)
+            .unwrap();
+    let vmt = patch.resolve(|_| Ok(None)).unwrap();
+    assert_eq!("patch", vmt.shader);
+    assert_eq!("materials/missing.vmt", vmt.patch.as_ref().unwrap().include);
+}
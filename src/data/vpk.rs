@@ -0,0 +1,278 @@
+use crate::BspResult;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const VPK_SIGNATURE: u32 = 0x55aa1234;
+/// Archive index used for files stored in the directory file itself
+const DIRECTORY_ARCHIVE_INDEX: u16 = 0x7fff;
+const ENTRY_TERMINATOR: u16 = 0xffff;
+
+/// A valve pak file, consisting of a `<name>_dir.vpk` directory file and numbered `<name>_<nnn>.vpk` data files
+#[derive(Debug, Clone)]
+pub struct Vpk {
+    path: PathBuf,
+    /// The directory file, files stored in the directory start at `data_offset`
+    directory: Vec<u8>,
+    data_offset: usize,
+    entries: HashMap<String, VpkEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VpkEntry {
+    pub crc32: u32,
+    pub archive_index: u16,
+    pub offset: u32,
+    pub length: u32,
+    /// Range of the data that is stored in the directory tree
+    preload: (usize, usize),
+}
+
+impl VpkEntry {
+    /// The total size of the file
+    pub fn size(&self) -> u64 {
+        (self.preload.1 - self.preload.0) as u64 + self.length as u64
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VpkError {
+    #[error("Invalid vpk signature {0:#x}")]
+    InvalidSignature(u32),
+    #[error("Unsupported vpk version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Unexpected end of vpk directory")]
+    UnexpectedEnd,
+    #[error("Malformed vpk directory entry")]
+    MalformedEntry,
+}
+
+impl Vpk {
+    /// Open a vpk from the path of its `_dir.vpk` file
+    pub fn open(path: impl AsRef<Path>) -> BspResult<Self> {
+        let path = path.as_ref();
+        let directory = std::fs::read(path)?;
+        Ok(Vpk::read(path, directory)?)
+    }
+
+    /// Parse the contents of a `_dir.vpk` file, `path` is used to locate the data files
+    pub fn read(path: impl Into<PathBuf>, directory: Vec<u8>) -> Result<Self, VpkError> {
+        let mut reader = DirectoryReader {
+            data: &directory,
+            position: 0,
+        };
+        let signature = reader.u32()?;
+        if signature != VPK_SIGNATURE {
+            return Err(VpkError::InvalidSignature(signature));
+        }
+        let version = reader.u32()?;
+        let tree_size = reader.u32()? as usize;
+        match version {
+            1 => {}
+            // file data, archive md5, other md5 and signature section sizes
+            2 => reader.skip(16)?,
+            version => return Err(VpkError::UnsupportedVersion(version)),
+        }
+        let data_offset = reader.position + tree_size;
+
+        let mut entries = HashMap::new();
+        loop {
+            let extension = reader.string()?;
+            if extension.is_empty() {
+                break;
+            }
+            loop {
+                let directory = reader.string()?;
+                if directory.is_empty() {
+                    break;
+                }
+                loop {
+                    let name = reader.string()?;
+                    if name.is_empty() {
+                        break;
+                    }
+                    let crc32 = reader.u32()?;
+                    let preload_size = reader.u16()? as usize;
+                    let archive_index = reader.u16()?;
+                    let offset = reader.u32()?;
+                    let length = reader.u32()?;
+                    if reader.u16()? != ENTRY_TERMINATOR {
+                        return Err(VpkError::MalformedEntry);
+                    }
+                    let preload_start = reader.position;
+                    reader.skip(preload_size)?;
+
+                    let mut path = String::new();
+                    if directory != " " {
+                        path.push_str(directory);
+                        path.push('/');
+                    }
+                    path.push_str(name);
+                    if extension != " " {
+                        path.push('.');
+                        path.push_str(extension);
+                    }
+                    entries.insert(
+                        path.to_ascii_lowercase(),
+                        VpkEntry {
+                            crc32,
+                            archive_index,
+                            offset,
+                            length,
+                            preload: (preload_start, preload_start + preload_size),
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(Vpk {
+            path: path.into(),
+            directory,
+            data_offset,
+            entries,
+        })
+    }
+
+    /// Get the paths of all files in the vpk
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&VpkEntry> {
+        self.entries.get(&normalize_path(name))
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.entry(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> BspResult<Option<Vec<u8>>> {
+        let Some(entry) = self.entry(name) else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        data.extend_from_slice(&self.directory[entry.preload.0..entry.preload.1]);
+        if entry.length == 0 {
+            return Ok(Some(data));
+        }
+
+        if entry.archive_index == DIRECTORY_ARCHIVE_INDEX {
+            let start = self.data_offset + entry.offset as usize;
+            let file_data = self
+                .directory
+                .get(start..start + entry.length as usize)
+                .ok_or(VpkError::UnexpectedEnd)?;
+            data.extend_from_slice(file_data);
+        } else {
+            let mut file = File::open(self.archive_path(entry.archive_index))?;
+            file.seek(SeekFrom::Start(entry.offset as u64))?;
+            file.take(entry.length as u64).read_to_end(&mut data)?;
+            if data.len() as u64 != entry.size() {
+                return Err(VpkError::UnexpectedEnd.into());
+            }
+        }
+        Ok(Some(data))
+    }
+
+    /// Get the path of a numbered data file
+    fn archive_path(&self, index: u16) -> PathBuf {
+        let name = self
+            .path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let base = name
+            .strip_suffix("_dir.vpk")
+            .or_else(|| name.strip_suffix(".vpk"))
+            .unwrap_or(name);
+        self.path.with_file_name(format!("{base}_{index:03}.vpk"))
+    }
+}
+
+/// Normalize a game path into the lowercase, forward slash separated form used for lookups
+pub(crate) fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .trim_start_matches('/')
+        .to_ascii_lowercase()
+}
+
+struct DirectoryReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> DirectoryReader<'a> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], VpkError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + N)
+            .ok_or(VpkError::UnexpectedEnd)?;
+        self.position += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> Result<u16, VpkError> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, VpkError> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), VpkError> {
+        if self.position + count > self.data.len() {
+            return Err(VpkError::UnexpectedEnd);
+        }
+        self.position += count;
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<&'a str, VpkError> {
+        let rest = &self.data[self.position.min(self.data.len())..];
+        let length = rest
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(VpkError::UnexpectedEnd)?;
+        self.position += length + 1;
+        std::str::from_utf8(&rest[..length]).map_err(|_| VpkError::MalformedEntry)
+    }
+}
+
+#[test]
+fn test_read_vpk() {
+    fn entry(tree: &mut Vec<u8>, preload: &[u8], offset: u32, length: u32) {
+        tree.extend_from_slice(&0u32.to_le_bytes());
+        tree.extend_from_slice(&(preload.len() as u16).to_le_bytes());
+        tree.extend_from_slice(&DIRECTORY_ARCHIVE_INDEX.to_le_bytes());
+        tree.extend_from_slice(&offset.to_le_bytes());
+        tree.extend_from_slice(&length.to_le_bytes());
+        tree.extend_from_slice(&ENTRY_TERMINATOR.to_le_bytes());
+        tree.extend_from_slice(preload);
+    }
+
+    let mut tree = Vec::new();
+    tree.extend_from_slice(b"vmt\0materials/concrete\0wall01\0");
+    entry(&mut tree, b"\"Light", 0, 14);
+    tree.extend_from_slice(b"\0\0txt\0 \0readme\0");
+    entry(&mut tree, b"hello", 0, 0);
+    tree.extend_from_slice(b"\0\0\0");
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&VPK_SIGNATURE.to_le_bytes());
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&(tree.len() as u32).to_le_bytes());
+    data.extend_from_slice(&tree);
+    data.extend_from_slice(b"mappedGeneric\"");
+
+    let vpk = Vpk::read("pak01_dir.vpk", data).unwrap();
+    assert_eq!(
+        Some(b"\"LightmappedGeneric\"".to_vec()),
+        vpk.get("Materials\\Concrete\\Wall01.vmt").unwrap()
+    );
+    assert_eq!(Some(b"hello".to_vec()), vpk.get("readme.txt").unwrap());
+    assert!(!vpk.has("materials/concrete/wall02.vmt"));
+    assert_eq!(PathBuf::from("pak01_002.vpk"), vpk.archive_path(2));
+}
@@ -0,0 +1,702 @@
+use crate::{BspResult, Vector};
+use binrw::{BinRead, BinReaderExt};
+use bitflags::bitflags;
+use std::io::Cursor;
+use thiserror::Error;
+
+const RESOURCE_LOW_RES_IMAGE: [u8; 3] = [0x01, 0, 0];
+const RESOURCE_HIGH_RES_IMAGE: [u8; 3] = [0x30, 0, 0];
+/// Resources with this flag store their value in the offset field instead of pointing to data
+const RESOURCE_NO_DATA: u8 = 0x02;
+
+/// A valve texture file
+#[derive(Debug, Clone)]
+pub struct Vtf {
+    pub header: VtfHeader,
+    data: Vec<u8>,
+    low_res_offset: Option<usize>,
+    high_res_offset: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum VtfError {
+    #[error("Unsupported vtf version {}.{}", .0[0], .0[1])]
+    UnsupportedVersion([u32; 2]),
+    #[error("Vtf doesn't contain any high resolution image data")]
+    NoImageData,
+    #[error("Decoding images with format {0:?} is not supported")]
+    UnsupportedFormat(VtfImageFormat),
+    #[error("Image mip {mip}, frame {frame}, face {face} does not exist in the vtf")]
+    InvalidImage { mip: u8, frame: u16, face: u8 },
+    #[error("Image data out of bounds of the vtf file")]
+    ImageOutOfBounds,
+}
+
+impl Vtf {
+    pub fn read(data: &[u8]) -> BspResult<Self> {
+        let header: VtfHeader = Cursor::new(data).read_le()?;
+        if header.version[0] != 7 {
+            return Err(VtfError::UnsupportedVersion(header.version).into());
+        }
+
+        let (low_res_offset, high_res_offset) = match &header.resources {
+            Some(resources) => {
+                let offset = |tag| {
+                    resources
+                        .entries
+                        .iter()
+                        .find(|entry| entry.tag == tag && entry.flags & RESOURCE_NO_DATA == 0)
+                        .map(|entry| entry.offset as usize)
+                };
+                (
+                    offset(RESOURCE_LOW_RES_IMAGE),
+                    offset(RESOURCE_HIGH_RES_IMAGE).ok_or(VtfError::NoImageData)?,
+                )
+            }
+            None => {
+                let low_res_size = header
+                    .low_res_format
+                    .image_size(header.low_res_width as u32, header.low_res_height as u32);
+                let start = header.header_size as usize;
+                (Some(start), start + low_res_size)
+            }
+        };
+
+        Ok(Vtf {
+            header,
+            data: data.to_vec(),
+            low_res_offset,
+            high_res_offset,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.header.width as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.header.height as u32
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.header.depth.max(1) as u32
+    }
+
+    pub fn flags(&self) -> VtfFlags {
+        self.header.flags
+    }
+
+    pub fn format(&self) -> VtfImageFormat {
+        self.header.high_res_format
+    }
+
+    pub fn mipmap_count(&self) -> u8 {
+        self.header.mipmap_count.max(1)
+    }
+
+    pub fn frame_count(&self) -> u16 {
+        self.header.frames.max(1)
+    }
+
+    /// The number of faces for each frame, 6 for cubemaps (or 7 if a sphere map is included)
+    pub fn face_count(&self) -> u8 {
+        if !self.header.flags.contains(VtfFlags::ENVMAP) {
+            1
+        } else if self.header.version[1] < 5 && self.header.first_frame != 0xffff {
+            7
+        } else {
+            6
+        }
+    }
+
+    /// Get the size of a mipmap level, level 0 being the full resolution image
+    pub fn mip_size(&self, mip: u8) -> (u32, u32) {
+        (
+            mip_dimension(self.width(), mip),
+            mip_dimension(self.height(), mip),
+        )
+    }
+
+    fn mip_depth(&self, mip: u8) -> u32 {
+        mip_dimension(self.depth(), mip)
+    }
+
+    /// Get the low resolution thumbnail of the texture
+    pub fn low_res_image(&self) -> Option<VtfImage<'_>> {
+        let format = self.header.low_res_format;
+        let (width, height) = (
+            self.header.low_res_width as u32,
+            self.header.low_res_height as u32,
+        );
+        if format == VtfImageFormat::None || width == 0 || height == 0 {
+            return None;
+        }
+        let start = self.low_res_offset?;
+        let data = self
+            .data
+            .get(start..start + format.image_size(width, height))?;
+        Some(VtfImage {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    /// Get the image for a mipmap level, frame and cubemap face
+    ///
+    /// For volume textures, the first slice is returned
+    pub fn image(&self, mip: u8, frame: u16, face: u8) -> Result<VtfImage<'_>, VtfError> {
+        if mip >= self.mipmap_count() || frame >= self.frame_count() || face >= self.face_count() {
+            return Err(VtfError::InvalidImage { mip, frame, face });
+        }
+        let format = self.format();
+        let images_per_mip = self.frame_count() as usize * self.face_count() as usize;
+
+        // mipmaps are stored from smallest to largest
+        let smaller_mips: usize = (mip + 1..self.mipmap_count())
+            .map(|level| {
+                let (width, height) = self.mip_size(level);
+                format.image_size(width, height) * images_per_mip * self.mip_depth(level) as usize
+            })
+            .sum();
+        let (width, height) = self.mip_size(mip);
+        let image_size = format.image_size(width, height);
+        let image_index = frame as usize * self.face_count() as usize + face as usize;
+        let start = self.high_res_offset
+            + smaller_mips
+            + image_index * self.mip_depth(mip) as usize * image_size;
+
+        let data = self
+            .data
+            .get(start..start + image_size)
+            .ok_or(VtfError::ImageOutOfBounds)?;
+        Ok(VtfImage {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+}
+
+#[derive(Debug, Clone, BinRead)]
+#[br(magic = b"VTF\0")]
+pub struct VtfHeader {
+    pub version: [u32; 2],
+    pub header_size: u32,
+    pub width: u16,
+    pub height: u16,
+    pub flags: VtfFlags,
+    pub frames: u16,
+    pub first_frame: u16,
+    #[br(pad_before = 4)]
+    pub reflectivity: Vector,
+    #[br(pad_before = 4)]
+    pub bumpmap_scale: f32,
+    #[br(map = |raw: i32| VtfImageFormat::from(raw))]
+    pub high_res_format: VtfImageFormat,
+    pub mipmap_count: u8,
+    #[br(map = |raw: i32| VtfImageFormat::from(raw))]
+    pub low_res_format: VtfImageFormat,
+    pub low_res_width: u8,
+    pub low_res_height: u8,
+    #[br(if(version[1] >= 2, 1))]
+    pub depth: u16,
+    #[br(if(version[1] >= 3))]
+    pub resources: Option<VtfResources>,
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct VtfResources {
+    #[br(pad_before = 3, pad_after = 8)]
+    pub count: u32,
+    #[br(count = count)]
+    pub entries: Vec<VtfResourceEntry>,
+}
+
+#[derive(Debug, Clone, BinRead)]
+pub struct VtfResourceEntry {
+    pub tag: [u8; 3],
+    pub flags: u8,
+    pub offset: u32,
+}
+
+#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtfFlags(u32);
+
+bitflags! {
+    impl VtfFlags: u32 {
+        const POINT_SAMPLE = 0x1;
+        const TRILINEAR = 0x2;
+        const CLAMP_S = 0x4;
+        const CLAMP_T = 0x8;
+        const ANISOTROPIC = 0x10;
+        const HINT_DXT5 = 0x20;
+        const PWL_CORRECTED = 0x40;
+        const NORMAL = 0x80;
+        const NO_MIP = 0x100;
+        const NO_LOD = 0x200;
+        const ALL_MIPS = 0x400;
+        const PROCEDURAL = 0x800;
+        const ONE_BIT_ALPHA = 0x1000;
+        const EIGHT_BIT_ALPHA = 0x2000;
+        const ENVMAP = 0x4000;
+        const RENDER_TARGET = 0x8000;
+        const DEPTH_RENDER_TARGET = 0x10000;
+        const NO_DEBUG_OVERRIDE = 0x20000;
+        const SINGLE_COPY = 0x40000;
+        const PRE_SRGB = 0x80000;
+        const NO_DEPTH_BUFFER = 0x800000;
+        const CLAMP_U = 0x2000000;
+        const VERTEX_TEXTURE = 0x4000000;
+        const SSBUMP = 0x8000000;
+        const BORDER = 0x20000000;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtfImageFormat {
+    None,
+    Rgba8888,
+    Abgr8888,
+    Rgb888,
+    Bgr888,
+    Rgb565,
+    I8,
+    Ia88,
+    P8,
+    A8,
+    Rgb888BlueScreen,
+    Bgr888BlueScreen,
+    Argb8888,
+    Bgra8888,
+    Dxt1,
+    Dxt3,
+    Dxt5,
+    Bgrx8888,
+    Bgr565,
+    Bgrx5551,
+    Bgra4444,
+    Dxt1OneBitAlpha,
+    Bgra5551,
+    Uv88,
+    Uvwq8888,
+    Rgba16161616F,
+    Rgba16161616,
+    Uvlx8888,
+    R32F,
+    Rgb323232F,
+    Rgba32323232F,
+    Unknown(i32),
+}
+
+impl From<i32> for VtfImageFormat {
+    fn from(value: i32) -> Self {
+        use VtfImageFormat::*;
+        match value {
+            -1 => None,
+            0 => Rgba8888,
+            1 => Abgr8888,
+            2 => Rgb888,
+            3 => Bgr888,
+            4 => Rgb565,
+            5 => I8,
+            6 => Ia88,
+            7 => P8,
+            8 => A8,
+            9 => Rgb888BlueScreen,
+            10 => Bgr888BlueScreen,
+            11 => Argb8888,
+            12 => Bgra8888,
+            13 => Dxt1,
+            14 => Dxt3,
+            15 => Dxt5,
+            16 => Bgrx8888,
+            17 => Bgr565,
+            18 => Bgrx5551,
+            19 => Bgra4444,
+            20 => Dxt1OneBitAlpha,
+            21 => Bgra5551,
+            22 => Uv88,
+            23 => Uvwq8888,
+            24 => Rgba16161616F,
+            25 => Rgba16161616,
+            26 => Uvlx8888,
+            27 => R32F,
+            28 => Rgb323232F,
+            29 => Rgba32323232F,
+            value => Unknown(value),
+        }
+    }
+}
+
+impl VtfImageFormat {
+    /// Size in bytes of a single pixel, or of a 4x4 block for compressed formats
+    fn block_size(&self) -> usize {
+        use VtfImageFormat::*;
+        match self {
+            None | Unknown(_) => 0,
+            I8 | P8 | A8 => 1,
+            Rgb565 | Ia88 | Bgr565 | Bgrx5551 | Bgra4444 | Bgra5551 | Uv88 => 2,
+            Rgb888 | Bgr888 | Rgb888BlueScreen | Bgr888BlueScreen => 3,
+            Rgba8888 | Abgr8888 | Argb8888 | Bgra8888 | Bgrx8888 | Uvwq8888 | Uvlx8888 | R32F => 4,
+            Rgba16161616F | Rgba16161616 => 8,
+            Rgb323232F => 12,
+            Rgba32323232F => 16,
+            Dxt1 | Dxt1OneBitAlpha => 8,
+            Dxt3 | Dxt5 => 16,
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(
+            self,
+            VtfImageFormat::Dxt1
+                | VtfImageFormat::Dxt1OneBitAlpha
+                | VtfImageFormat::Dxt3
+                | VtfImageFormat::Dxt5
+        )
+    }
+
+    /// Whether the format stores floating point or high precision values
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self,
+            VtfImageFormat::Rgba16161616F
+                | VtfImageFormat::Rgba16161616
+                | VtfImageFormat::R32F
+                | VtfImageFormat::Rgb323232F
+                | VtfImageFormat::Rgba32323232F
+        )
+    }
+
+    /// Get the size in bytes of an image with this format
+    pub fn image_size(&self, width: u32, height: u32) -> usize {
+        if self.is_compressed() {
+            width.div_ceil(4) as usize * height.div_ceil(4) as usize * self.block_size()
+        } else {
+            width as usize * height as usize * self.block_size()
+        }
+    }
+}
+
+/// A single image from a vtf file
+#[derive(Debug, Clone)]
+pub struct VtfImage<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: VtfImageFormat,
+    /// The raw image data in the format of the image
+    pub data: &'a [u8],
+}
+
+impl VtfImage<'_> {
+    /// Decode the image into 8 bit rgba pixels
+    ///
+    /// High dynamic range values are clamped to the `0..=1` range
+    pub fn to_rgba8(&self) -> Result<Vec<u8>, VtfError> {
+        if self.format.is_hdr() {
+            return Ok(self
+                .to_rgba_f32()?
+                .into_iter()
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect());
+        }
+        if self.format.is_compressed() {
+            return Ok(self.decode_blocks());
+        }
+
+        use VtfImageFormat::*;
+        let pixel = |bytes: &[u8]| -> [u8; 4] {
+            match self.format {
+                Rgba8888 | Uvwq8888 | Uvlx8888 => [bytes[0], bytes[1], bytes[2], bytes[3]],
+                Abgr8888 => [bytes[3], bytes[2], bytes[1], bytes[0]],
+                Argb8888 => [bytes[1], bytes[2], bytes[3], bytes[0]],
+                Bgra8888 => [bytes[2], bytes[1], bytes[0], bytes[3]],
+                Bgrx8888 => [bytes[2], bytes[1], bytes[0], 255],
+                Rgb888 => [bytes[0], bytes[1], bytes[2], 255],
+                Bgr888 => [bytes[2], bytes[1], bytes[0], 255],
+                Rgb888BlueScreen => blue_screen([bytes[0], bytes[1], bytes[2]]),
+                Bgr888BlueScreen => blue_screen([bytes[2], bytes[1], bytes[0]]),
+                I8 => [bytes[0], bytes[0], bytes[0], 255],
+                Ia88 => [bytes[0], bytes[0], bytes[0], bytes[1]],
+                A8 => [0, 0, 0, bytes[0]],
+                Uv88 => [bytes[0], bytes[1], 0, 255],
+                Rgb565 => {
+                    let [r, g, b] = unpack_565(u16::from_le_bytes([bytes[0], bytes[1]]));
+                    [b, g, r, 255]
+                }
+                Bgr565 => {
+                    let [r, g, b] = unpack_565(u16::from_le_bytes([bytes[0], bytes[1]]));
+                    [r, g, b, 255]
+                }
+                Bgrx5551 | Bgra5551 => {
+                    let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    let channel = |shift: u16| expand_bits((value >> shift) & 0x1f, 5);
+                    let alpha = if self.format == Bgrx5551 || value & 0x8000 != 0 {
+                        255
+                    } else {
+                        0
+                    };
+                    [channel(10), channel(5), channel(0), alpha]
+                }
+                Bgra4444 => {
+                    let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    let channel = |shift: u16| expand_bits((value >> shift) & 0xf, 4);
+                    [channel(8), channel(4), channel(0), channel(12)]
+                }
+                _ => unreachable!(),
+            }
+        };
+
+        match self.format {
+            None | P8 | Unknown(_) => Err(VtfError::UnsupportedFormat(self.format)),
+            _ => Ok(self
+                .data
+                .chunks_exact(self.format.block_size())
+                .flat_map(pixel)
+                .collect()),
+        }
+    }
+
+    /// Decode the image into floating point rgba pixels
+    ///
+    /// Low dynamic range values are mapped to the `0..=1` range
+    pub fn to_rgba_f32(&self) -> Result<Vec<f32>, VtfError> {
+        let floats = |bytes: &[u8]| -> Vec<f32> {
+            bytes
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                .collect()
+        };
+        Ok(match self.format {
+            VtfImageFormat::Rgba16161616F => self
+                .data
+                .chunks_exact(2)
+                .map(|value| f16_to_f32(u16::from_le_bytes([value[0], value[1]])))
+                .collect(),
+            VtfImageFormat::Rgba16161616 => self
+                .data
+                .chunks_exact(2)
+                .map(|value| u16::from_le_bytes([value[0], value[1]]) as f32 / u16::MAX as f32)
+                .collect(),
+            VtfImageFormat::Rgba32323232F => floats(self.data),
+            VtfImageFormat::Rgb323232F => floats(self.data)
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 1.0])
+                .collect(),
+            VtfImageFormat::R32F => floats(self.data)
+                .into_iter()
+                .flat_map(|r| [r, r, r, 1.0])
+                .collect(),
+            _ => self
+                .to_rgba8()?
+                .into_iter()
+                .map(|value| value as f32 / 255.0)
+                .collect(),
+        })
+    }
+
+    fn decode_blocks(&self) -> Vec<u8> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut pixels = vec![0; width * height * 4];
+        let blocks_wide = width.div_ceil(4);
+
+        for (index, block) in self.data.chunks_exact(self.format.block_size()).enumerate() {
+            let decoded = match self.format {
+                VtfImageFormat::Dxt3 => {
+                    let mut colors = decode_color_block(&block[8..], false);
+                    for (i, color) in colors.iter_mut().enumerate() {
+                        let alpha = (block[i / 2] >> ((i % 2) * 4)) & 0xf;
+                        color[3] = expand_bits(alpha as u16, 4);
+                    }
+                    colors
+                }
+                VtfImageFormat::Dxt5 => {
+                    let mut colors = decode_color_block(&block[8..], false);
+                    let alphas = decode_alpha_block(&block[..8]);
+                    for (color, alpha) in colors.iter_mut().zip(alphas) {
+                        color[3] = alpha;
+                    }
+                    colors
+                }
+                _ => decode_color_block(block, true),
+            };
+
+            let (block_x, block_y) = ((index % blocks_wide) * 4, (index / blocks_wide) * 4);
+            for (i, color) in decoded.iter().enumerate() {
+                let (x, y) = (block_x + i % 4, block_y + i / 4);
+                if x < width && y < height {
+                    let offset = (y * width + x) * 4;
+                    pixels[offset..offset + 4].copy_from_slice(color);
+                }
+            }
+        }
+        pixels
+    }
+}
+
+fn blue_screen(rgb: [u8; 3]) -> [u8; 4] {
+    if rgb == [0, 0, 255] {
+        [0, 0, 0, 0]
+    } else {
+        [rgb[0], rgb[1], rgb[2], 255]
+    }
+}
+
+/// Expand a value with `bits` bits to the full 8 bit range
+fn expand_bits(value: u16, bits: u32) -> u8 {
+    let max = (1u32 << bits) - 1;
+    ((value as u32 * 255 + max / 2) / max) as u8
+}
+
+fn unpack_565(value: u16) -> [u8; 3] {
+    [
+        expand_bits(value >> 11, 5),
+        expand_bits((value >> 5) & 0x3f, 6),
+        expand_bits(value & 0x1f, 5),
+    ]
+}
+
+/// Decode the color part of a dxt block into 16 pixels
+fn decode_color_block(block: &[u8], allow_transparent: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let [r0, g0, b0] = unpack_565(color0).map(u32::from);
+    let [r1, g1, b1] = unpack_565(color1).map(u32::from);
+    let mix = |a: u32, b: u32, weight_a: u32, weight_b: u32| {
+        ((a * weight_a + b * weight_b) / (weight_a + weight_b)) as u8
+    };
+
+    let palette = if color0 > color1 || !allow_transparent {
+        [
+            [r0 as u8, g0 as u8, b0 as u8, 255],
+            [r1 as u8, g1 as u8, b1 as u8, 255],
+            [mix(r0, r1, 2, 1), mix(g0, g1, 2, 1), mix(b0, b1, 2, 1), 255],
+            [mix(r0, r1, 1, 2), mix(g0, g1, 1, 2), mix(b0, b1, 1, 2), 255],
+        ]
+    } else {
+        [
+            [r0 as u8, g0 as u8, b0 as u8, 255],
+            [r1 as u8, g1 as u8, b1 as u8, 255],
+            [mix(r0, r1, 1, 1), mix(g0, g1, 1, 1), mix(b0, b1, 1, 1), 255],
+            [0, 0, 0, 0],
+        ]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|i| palette[((indices >> (i * 2)) & 0b11) as usize])
+}
+
+/// Decode the interpolated alpha part of a dxt5 block into 16 alpha values
+fn decode_alpha_block(block: &[u8]) -> [u8; 16] {
+    let (alpha0, alpha1) = (block[0] as u32, block[1] as u32);
+    let palette: [u8; 8] = std::array::from_fn(|i| {
+        let i = i as u32;
+        match i {
+            0 => alpha0 as u8,
+            1 => alpha1 as u8,
+            _ if alpha0 > alpha1 => (((8 - i) * alpha0 + (i - 1) * alpha1) / 7) as u8,
+            6 => 0,
+            7 => 255,
+            _ => (((6 - i) * alpha0 + (i - 1) * alpha1) / 5) as u8,
+        }
+    });
+
+    let mut bits = [0; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    std::array::from_fn(|i| palette[((indices >> (i * 3)) & 0b111) as usize])
+}
+
+/// Size of a texture dimension at a mip level, mip levels past the smallest are 1 pixel
+fn mip_dimension(size: u32, mip: u8) -> u32 {
+    size.checked_shr(mip as u32).unwrap_or(0).max(1)
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[test]
+fn test_decode_dxt1() {
+    // a block with white and black as endpoints, alternating rows of each palette entry
+    let block = [
+        0xff, 0xff, 0x00, 0x00, 0b00000000, 0b01010101, 0b10101010, 0b11111111,
+    ];
+    let image = VtfImage {
+        width: 4,
+        height: 4,
+        format: VtfImageFormat::Dxt1,
+        data: &block,
+    };
+    let pixels = image.to_rgba8().unwrap();
+    assert_eq!([255, 255, 255, 255], pixels[0..4]);
+    assert_eq!([0, 0, 0, 255], pixels[16..20]);
+    assert_eq!([170, 170, 170, 255], pixels[32..36]);
+    assert_eq!([85, 85, 85, 255], pixels[48..52]);
+}
+
+#[test]
+fn test_mip_dimension() {
+    assert_eq!(256, mip_dimension(256, 0));
+    assert_eq!(32, mip_dimension(256, 3));
+    assert_eq!(1, mip_dimension(256, 12));
+    assert_eq!(1, mip_dimension(256, 32));
+    assert_eq!(1, mip_dimension(u32::MAX, 255));
+}
+
+#[test]
+fn test_f16_to_f32() {
+    assert_eq!(1.0, f16_to_f32(0x3c00));
+    assert_eq!(-2.0, f16_to_f32(0xc000));
+    assert_eq!(0.5, f16_to_f32(0x3800));
+    assert_eq!(65504.0, f16_to_f32(0x7bff));
+}
+
+#[test]
+fn test_read_vtf() {
+    // 7.2 header for a 2x2 bgra8888 texture with 2 mipmaps and no thumbnail
+    let mut data = Vec::new();
+    data.extend_from_slice(b"VTF\0");
+    data.extend_from_slice(&[7, 0, 0, 0, 2, 0, 0, 0]);
+    data.extend_from_slice(&80u32.to_le_bytes());
+    data.extend_from_slice(&[2, 0, 2, 0]);
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&[1, 0, 0, 0]);
+    data.extend_from_slice(&[0; 4 + 12 + 4]);
+    data.extend_from_slice(&1f32.to_le_bytes());
+    data.extend_from_slice(&12i32.to_le_bytes());
+    data.push(2);
+    data.extend_from_slice(&(-1i32).to_le_bytes());
+    data.extend_from_slice(&[0, 0, 1, 0]);
+    data.resize(80, 0);
+    // 1x1 mip
+    data.extend_from_slice(&[1, 2, 3, 4]);
+    // 2x2 mip
+    data.extend_from_slice(&[0, 0, 255, 255].repeat(4));
+
+    let vtf = Vtf::read(&data).unwrap();
+    assert_eq!(VtfImageFormat::Bgra8888, vtf.format());
+    assert_eq!(2, vtf.mipmap_count());
+    assert_eq!(1, vtf.face_count());
+    assert!(vtf.low_res_image().is_none());
+    assert_eq!(
+        [255, 0, 0, 255].repeat(4),
+        vtf.image(0, 0, 0).unwrap().to_rgba8().unwrap()
+    );
+    assert_eq!(
+        vec![3, 2, 1, 4],
+        vtf.image(1, 0, 0).unwrap().to_rgba8().unwrap()
+    );
+    assert!(vtf.image(2, 0, 0).is_err());
+}
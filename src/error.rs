@@ -39,6 +39,22 @@ pub enum BspError {
     LumpVersion(UnsupportedLumpVersion),
     #[error(transparent)]
     Zip(#[from] ZipError),
+    #[error("packfile entry {0:?} would be extracted outside of the target directory")]
+    UnsafePackfilePath(String),
+    #[error("error while decompressing packfile entry {name:?}")]
+    PackfileDecompress {
+        name: String,
+        #[source]
+        error: lzma_rs::error::Error,
+    },
+    #[error("files can't be added to a packfile with compression method {0}")]
+    UnsupportedPackfileCompression(u16),
+    #[error(transparent)]
+    Material(#[from] VmtError),
+    #[error(transparent)]
+    Texture(#[from] VtfError),
+    #[error(transparent)]
+    Vpk(#[from] VpkError),
 }
 
 impl From<binrw::Error> for BspError {
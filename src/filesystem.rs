@@ -0,0 +1,152 @@
+use crate::data::normalize_path;
+use crate::{material_path, BspResult, Packfile, Vmt, Vpk};
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+/// A layered view over the packfile, game directories and vpk archives
+///
+/// Files are looked up in each source in the order they were added, mirroring the search paths
+/// from a game's `gameinfo.txt`. Paths are case-insensitive.
+#[derive(Debug, Clone, Default)]
+pub struct FileSystem {
+    sources: Vec<FileSource>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FileSource {
+    Pack(Packfile),
+    Directory(PathBuf),
+    Vpk(Vpk),
+}
+
+impl FileSystem {
+    pub fn new() -> Self {
+        FileSystem::default()
+    }
+
+    pub fn sources(&self) -> &[FileSource] {
+        &self.sources
+    }
+
+    /// Add a packfile to the end of the search path
+    pub fn add_pack(&mut self, pack: Packfile) {
+        self.sources.push(FileSource::Pack(pack));
+    }
+
+    /// Add a loose directory to the end of the search path
+    pub fn add_directory(&mut self, path: impl Into<PathBuf>) {
+        self.sources.push(FileSource::Directory(path.into()));
+    }
+
+    /// Open a vpk from the path of its `_dir.vpk` file and add it to the end of the search path
+    pub fn add_vpk(&mut self, path: impl AsRef<Path>) -> BspResult<()> {
+        self.sources.push(FileSource::Vpk(Vpk::open(path)?));
+        Ok(())
+    }
+
+    /// Read a file from the first source that contains it
+    pub fn get(&self, path: &str) -> BspResult<Option<Vec<u8>>> {
+        let path = normalize_path(path);
+        for source in &self.sources {
+            if let Some(data) = source.get(&path)? {
+                return Ok(Some(data));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn has(&self, path: &str) -> BspResult<bool> {
+        let path = normalize_path(path);
+        for source in &self.sources {
+            if source.has(&path)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Load a material by name and resolve `patch` materials
+    pub fn material(&self, name: &str) -> BspResult<Option<Vmt>> {
+        let Some(data) = self.get(&material_path(name))? else {
+            return Ok(None);
+        };
+        let vmt = Vmt::parse(&String::from_utf8_lossy(&data))?;
+        Ok(Some(vmt.resolve(|path| self.get(path))?))
+    }
+}
+
+impl FileSource {
+    fn get(&self, path: &str) -> BspResult<Option<Vec<u8>>> {
+        match self {
+            FileSource::Pack(pack) => pack.get_ignore_case(path),
+            FileSource::Directory(root) => match find_ignore_case(root, path) {
+                Some(file) => Ok(Some(std::fs::read(file)?)),
+                None => Ok(None),
+            },
+            FileSource::Vpk(vpk) => vpk.get(path),
+        }
+    }
+
+    fn has(&self, path: &str) -> BspResult<bool> {
+        match self {
+            FileSource::Pack(pack) => pack.has_ignore_case(path),
+            FileSource::Directory(root) => Ok(find_ignore_case(root, path).is_some()),
+            FileSource::Vpk(vpk) => Ok(vpk.has(path)),
+        }
+    }
+}
+
+/// Find a file in a directory, ignoring the case of the path on case-sensitive filesystems
+fn find_ignore_case(root: &Path, path: &str) -> Option<PathBuf> {
+    if path.split('/').any(|segment| segment == "..") {
+        return None;
+    }
+    let exact = root.join(path);
+    if exact.is_file() {
+        return Some(exact);
+    }
+
+    let mut current = root.to_path_buf();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let candidate = current.join(segment);
+        current = if candidate.exists() {
+            candidate
+        } else {
+            read_dir(&current)
+                .ok()?
+                .filter_map(Result::ok)
+                .find(|entry| {
+                    entry
+                        .file_name()
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case(segment)
+                })?
+                .path()
+        };
+    }
+    current.is_file().then_some(current)
+}
+
+#[test]
+fn test_pack_ignore_case() {
+    use crate::PackfileCompression;
+    use std::borrow::Cow;
+
+    // an empty zip only consists of the end of central directory record
+    let mut empty = 0x06054b50u32.to_le_bytes().to_vec();
+    empty.resize(22, 0);
+    let mut pack = Packfile::read(Cow::Owned(empty)).unwrap();
+    pack.insert(
+        "materials/Concrete/Wall02.vmt",
+        b"\"LightmappedGeneric\" { \"$basetexture\" \"concrete/wall02\" }",
+        PackfileCompression::Stored,
+    )
+    .unwrap();
+
+    let mut fs = FileSystem::new();
+    fs.add_pack(pack);
+    assert!(fs.has("materials/concrete/wall02.vmt").unwrap());
+    assert!(fs.has("Materials\\Concrete\\WALL02.vmt").unwrap());
+    assert!(fs.material("concrete/wall02").unwrap().is_some());
+    assert!(!fs.has("materials/concrete/wall03.vmt").unwrap());
+}
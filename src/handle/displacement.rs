@@ -30,6 +30,17 @@ impl<'a> Handle<'a, DisplacementInfo> {
             .flat_map(|i| self.bsp.displacement_vertex(i as usize))
     }
 
+    /// Get the tags for the triangles of the displacement
+    ///
+    /// Triangles are stored two per quad, row by row
+    pub fn displacement_triangles(
+        &self,
+    ) -> impl Iterator<Item = Handle<'a, DisplacementTriangle>> + use<'a> {
+        (self.displacement_triangle_tag_start
+            ..(self.displacement_triangle_tag_start + self.triangle_count()))
+            .flat_map(|i| self.bsp.displacement_triangle(i as usize))
+    }
+
     pub fn face(&self) -> Option<Handle<'a, Face>> {
         self.bsp.face(self.map_face as usize)
     }
@@ -60,8 +60,17 @@ impl<'a> Handle<'a, Face> {
 
     /// Triangulate the face
     ///
-    /// Triangulation only works for faces that can be turned into a triangle fan trivially
+    /// If the face has primitives (generated by vbsp to fix t-junctions) those are used for the
+    /// triangulation, otherwise the face is turned into a triangle fan
     pub fn triangulate(&self) -> impl Iterator<Item = [Vector; 3]> + 'a {
+        if self.primitive_count() > 0 {
+            Either::Left(self.triangulate_primitives())
+        } else {
+            Either::Right(self.triangulate_fan())
+        }
+    }
+
+    fn triangulate_fan(&self) -> impl Iterator<Item = [Vector; 3]> + 'a {
         let mut vertices = self.vertices();
 
         let a = vertices.next().expect("face with <3 points");
@@ -74,6 +83,38 @@ impl<'a> Handle<'a, Face> {
         })
     }
 
+    fn triangulate_primitives(&self) -> impl Iterator<Item = [Vector; 3]> + 'a {
+        let face_vertices: Vec<Vector> = self.vertices().map(|vertex| vertex.position).collect();
+        self.primitives().flat_map(move |primitive| {
+            // primitives without vertices of their own index into the vertices of the face
+            let vertices = if primitive.vertex_count > 0 {
+                primitive.vertices().collect()
+            } else {
+                face_vertices.clone()
+            };
+            primitive
+                .triangles(primitive.indices())
+                .into_iter()
+                .filter_map(move |[a, b, c]| {
+                    Some([
+                        *vertices.get(c as usize)?,
+                        *vertices.get(b as usize)?,
+                        *vertices.get(a as usize)?,
+                    ])
+                })
+        })
+    }
+
+    /// Get the primitives of the face
+    pub fn primitives(&self) -> impl Iterator<Item = Handle<'a, Primitive>> + 'a {
+        let start = self.first_primitive_index as usize;
+        let end = start + self.primitive_count() as usize;
+        let bsp = self.bsp;
+        bsp.primitives[start..end]
+            .iter()
+            .map(move |primitive| Handle::new(bsp, primitive))
+    }
+
     pub fn displacement(&self) -> Option<Handle<'a, DisplacementInfo>> {
         self.bsp.displacement(self.displacement_info as usize)
     }
@@ -92,3 +133,21 @@ impl<'a> Handle<'a, Face> {
         self.bsp.plane(self.plane_num as usize).unwrap().normal
     }
 }
+
+impl<'a> Handle<'a, Primitive> {
+    /// Get the vertex indices of the primitive
+    pub fn indices(&self) -> &'a [u16] {
+        let start = self.first_index as usize;
+        let end = start + self.index_count as usize;
+        &self.bsp.primitive_indices[start..end]
+    }
+
+    /// Get the vertices stored in the primitive
+    pub fn vertices(&self) -> impl Iterator<Item = Vector> + 'a {
+        let start = self.first_vertex as usize;
+        let end = start + self.vertex_count as usize;
+        self.bsp.primitive_vertices[start..end]
+            .iter()
+            .map(|vertex| vertex.position)
+    }
+}
@@ -1,8 +1,45 @@
 use super::Handle;
 use crate::data::*;
+use crate::BspResult;
 
 impl<'a> Handle<'a, StaticPropLump> {
     pub fn model(&self) -> &'a str {
         self.bsp.static_props.dict.name[self.prop_type as usize].as_str()
     }
+
+    /// Get the indexes of all leaves this prop is located in
+    pub fn leaf_indexes(&self) -> &'a [u16] {
+        let start = self.first_leaf as usize;
+        let end = start + self.leaf_count as usize;
+        &self.bsp.static_props.leaf.leaves[start..end]
+    }
+
+    /// Get all leaves this prop is located in
+    pub fn leaves(&self) -> impl Iterator<Item = Handle<'a, Leaf>> + use<'a> {
+        let bsp = self.bsp;
+        self.leaf_indexes()
+            .iter()
+            .filter_map(move |leaf| bsp.leaf(*leaf as usize))
+    }
+
+    /// Get the index of this prop in the static prop lump
+    pub fn index(&self) -> usize {
+        self.index_in(&self.bsp.static_props.props.props)
+    }
+
+    /// Get the baked per-vertex lighting for this prop from the packfile
+    ///
+    /// Returns `None` if the map contains no vertex lighting for the prop
+    pub fn vertex_lighting(&self, hdr: bool) -> BspResult<Option<VertexLighting>> {
+        let name = if hdr {
+            format!("sp_hdr_{}.vhv", self.index())
+        } else {
+            format!("sp_{}.vhv", self.index())
+        };
+        self.bsp
+            .pack
+            .get_ignore_case(&name)?
+            .map(|data| VertexLighting::read(&data))
+            .transpose()
+    }
 }
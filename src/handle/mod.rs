@@ -1,11 +1,15 @@
 mod displacement;
 mod face;
 mod game;
+mod occlusion;
+mod overlay;
 
 use crate::data::*;
-use crate::Bsp;
+use crate::{Bsp, BspResult};
 use ahash::RandomState;
+use bv::BitVec;
 use std::fmt::{Debug, Formatter};
+use std::mem::size_of;
 use std::ops::Deref;
 
 /// A handle represents a data structure in the bsp file and the bsp file containing it.
@@ -49,6 +53,11 @@ impl<'a, T> Handle<'a, T> {
     pub fn new(bsp: &'a Bsp, data: &'a T) -> Self {
         Handle { bsp, data }
     }
+
+    /// Get the position of the handled item in a list of items it's part of
+    fn index_in(&self, list: &[T]) -> usize {
+        (self.data as *const T as usize - list.as_ptr() as usize) / size_of::<T>()
+    }
 }
 
 impl<'a> Handle<'a, Model> {
@@ -66,6 +75,43 @@ impl<'a> Handle<'a, Model> {
     pub fn textures(&self) -> impl Iterator<Item = Handle<'_, TextureInfo>> {
         self.bsp.textures()
     }
+
+    /// Get the physics collision data for the model
+    pub fn collision(&self) -> Option<Handle<'a, PhysCollideModel>> {
+        let index = self.index_in(&self.bsp.models);
+        self.bsp
+            .phys_collide
+            .models
+            .iter()
+            .find(|model| model.model_index as usize == index)
+            .map(|model| Handle::new(self.bsp, model))
+    }
+}
+
+/// A brush model placed in the world by an entity
+#[derive(Debug, Clone)]
+pub struct ModelPlacement<'a> {
+    pub model: Handle<'a, Model>,
+    pub transform: EntityTransform,
+}
+
+impl<'a> ModelPlacement<'a> {
+    /// Get the faces of the model with their vertices transformed into world space
+    pub fn world_faces(&self) -> impl Iterator<Item = Vec<Vector>> + '_ {
+        let transform = self.transform;
+        self.model.faces().map(move |face| {
+            face.vertex_positions()
+                .map(|position| transform.apply(position))
+                .collect()
+        })
+    }
+}
+
+impl<'a> Handle<'a, PhysCollideModel> {
+    /// Get the model this collision data belongs to
+    pub fn model(&self) -> Handle<'a, Model> {
+        Handle::new(self.bsp, &self.bsp.models[self.model_index as usize])
+    }
 }
 
 impl Handle<'_, Node> {
@@ -88,15 +134,7 @@ impl<'a> Handle<'a, Leaf> {
             Some(
                 bsp.leaves
                     .iter()
-                    .filter(move |leaf| {
-                        if leaf.cluster == cluster {
-                            true
-                        } else if leaf.cluster > 0 {
-                            visible_clusters[leaf.cluster as u64]
-                        } else {
-                            false
-                        }
-                    })
+                    .filter(move |leaf| cluster_visible(&visible_clusters, cluster, leaf.cluster))
                     .map(move |leaf| Handle { bsp, data: leaf }),
             )
         }
@@ -111,6 +149,66 @@ impl<'a> Handle<'a, Leaf> {
             .iter()
             .filter_map(move |leaf_face| bsp.face(leaf_face.face as usize))
     }
+
+    /// Get the water volume this leaf is part of, if any
+    pub fn water_data(&self) -> Option<Handle<'a, LeafWaterData>> {
+        let bsp = self.bsp;
+        (self.leaf_watter_data_id >= 0)
+            .then(|| bsp.leaf_water_data.get(self.leaf_watter_data_id as usize))
+            .flatten()
+            .map(|water| Handle::new(bsp, water))
+    }
+
+    /// Get the minimum distance from this leaf to the nearest water volume
+    pub fn minimum_distance_to_water(&self) -> Option<u16> {
+        self.bsp
+            .leaf_minimum_distance_to_water
+            .get(self.index_in(&self.bsp.leaves))
+            .copied()
+    }
+
+    /// Get the index of this leaf in the leaf lump
+    pub fn index(&self) -> usize {
+        self.index_in(&self.bsp.leaves)
+    }
+
+    /// Get all static props located in this leaf
+    pub fn static_props(&self) -> impl Iterator<Item = Handle<'a, StaticPropLump>> + use<'a> {
+        let index = self.index() as u16;
+        self.bsp
+            .static_props()
+            .filter(move |prop| prop.leaf_indexes().contains(&index))
+    }
+
+    /// Get all static props that are potentially visible from this leaf
+    pub fn visible_static_props(
+        &self,
+    ) -> Option<impl Iterator<Item = Handle<'a, StaticPropLump>> + use<'a>> {
+        let cluster = self.cluster;
+        let bsp = self.bsp;
+
+        if cluster < 0 {
+            None
+        } else {
+            let visible_clusters = bsp.vis_data.visible_clusters(cluster);
+            Some(bsp.static_props().filter(move |prop| {
+                prop.leaf_indexes().iter().any(|leaf| {
+                    let leaf_cluster = bsp
+                        .leaves
+                        .get(*leaf as usize)
+                        .map_or(-1, |leaf| leaf.cluster);
+                    cluster_visible(&visible_clusters, cluster, leaf_cluster)
+                })
+            }))
+        }
+    }
+}
+
+/// Check if a leaf cluster is visible from `cluster`
+///
+/// Cluster 0 is a regular cluster, leaves outside the map have a negative cluster and are never visible
+fn cluster_visible(visible_clusters: &BitVec<u8>, cluster: i16, leaf_cluster: i16) -> bool {
+    leaf_cluster == cluster || (leaf_cluster >= 0 && visible_clusters[leaf_cluster as u64])
 }
 
 impl<'a> Handle<'a, TextureInfo> {
@@ -124,6 +222,13 @@ impl<'a> Handle<'a, TextureInfo> {
         self.texture_data().name()
     }
 
+    /// Load the material for this texture from the packfile
+    ///
+    /// See [`Handle<TextureData>::material`]
+    pub fn material(&self) -> BspResult<Option<Vmt>> {
+        self.texture_data().material()
+    }
+
     /// Get a color that is unique but deterministic for this texture
     pub fn debug_color(&self) -> [u8; 3] {
         self.texture_data().debug_color()
@@ -161,6 +266,19 @@ impl<'a> Handle<'a, TextureData> {
         }
     }
 
+    /// Load the material for this texture from the packfile
+    ///
+    /// Returns `None` if the material isn't embedded in the map, `patch` materials are resolved
+    /// from the packfile as well.
+    pub fn material(&self) -> BspResult<Option<Vmt>> {
+        let pack = &self.bsp.pack;
+        let Some(data) = pack.get_ignore_case(&material_path(self.name()))? else {
+            return Ok(None);
+        };
+        let vmt = Vmt::parse(&String::from_utf8_lossy(&data))?;
+        Ok(Some(vmt.resolve(|path| pack.get_ignore_case(path))?))
+    }
+
     /// Get a color that is unique but deterministic for this texture
     pub fn debug_color(&self) -> [u8; 3] {
         let name_hash = RandomState::with_seeds(0, 0, 0, 0)
@@ -169,3 +287,35 @@ impl<'a> Handle<'a, TextureData> {
         [name_hash[0], name_hash[1], name_hash[2]]
     }
 }
+
+impl Handle<'_, CubeMapSample> {
+    /// Get the cubemap texture from the packfile
+    ///
+    /// `map_name` is the name of the bsp file, without the `.bsp` extension
+    pub fn texture(&self, map_name: &str, hdr: bool) -> BspResult<Option<Vec<u8>>> {
+        self.bsp
+            .pack
+            .get_ignore_case(&self.texture_path(map_name, hdr))
+    }
+
+    /// Get the decoded cubemap texture from the packfile
+    ///
+    /// `map_name` is the name of the bsp file, without the `.bsp` extension
+    pub fn vtf(&self, map_name: &str, hdr: bool) -> BspResult<Option<Vtf>> {
+        self.texture(map_name, hdr)?
+            .map(|data| Vtf::read(&data))
+            .transpose()
+    }
+}
+
+impl<'a> Handle<'a, LeafWaterData> {
+    /// Get the texture of the water surface
+    pub fn surface_texture(&self) -> Option<Handle<'a, TextureInfo>> {
+        self.bsp.texture_info(self.surface_texture_info as usize)
+    }
+
+    /// Get the depth of a position below the water surface
+    pub fn depth_at(&self, point: Vector) -> f32 {
+        self.surface_z - point.z
+    }
+}
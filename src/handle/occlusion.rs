@@ -0,0 +1,57 @@
+use super::Handle;
+use crate::data::*;
+
+impl<'a> Handle<'a, Occluder> {
+    /// Get all polygons making up the occluder
+    pub fn polygons(&self) -> impl Iterator<Item = Handle<'a, OccluderPolygon>> + use<'a> {
+        let start = self.first_polygon as usize;
+        let end = start + self.polygon_count as usize;
+        let bsp = self.bsp;
+        bsp.occlusion.polygons[start..end]
+            .iter()
+            .map(move |polygon| Handle::new(bsp, polygon))
+    }
+
+    /// Triangulate all polygons of the occluder
+    pub fn triangulate(&self) -> impl Iterator<Item = [Vector; 3]> + use<'a> {
+        self.polygons().flat_map(|polygon| polygon.triangulate())
+    }
+
+    /// Get the total surface area of the occluder's polygons
+    pub fn surface_area(&self) -> f32 {
+        self.triangulate()
+            .map(|[a, b, c]| (b - a).cross(c - a).length_squared().sqrt() / 2.0)
+            .sum()
+    }
+}
+
+impl<'a> Handle<'a, OccluderPolygon> {
+    /// Get the plane the polygon lies in
+    pub fn plane(&self) -> Handle<'a, Plane> {
+        self.bsp.plane(self.data.plane as usize).unwrap()
+    }
+
+    /// Get the positions of all vertices making up the polygon
+    pub fn vertices(&self) -> impl Iterator<Item = Vector> + use<'a> {
+        let start = self.first_vertex_index as usize;
+        let end = start + self.vertex_count as usize;
+        let bsp = self.bsp;
+        bsp.occlusion.vertex_indices[start..end]
+            .iter()
+            .map(move |index| bsp.vertices[*index as usize].position)
+    }
+
+    /// Triangulate the polygon
+    pub fn triangulate(&self) -> impl Iterator<Item = [Vector; 3]> + use<'a> {
+        let mut vertices = self.vertices();
+
+        let a = vertices.next();
+        let mut b = vertices.next();
+
+        vertices.filter_map(move |c| {
+            let points = [c, b?, a?];
+            b = Some(c);
+            Some(points)
+        })
+    }
+}
@@ -0,0 +1,50 @@
+use super::Handle;
+use crate::data::*;
+
+impl<'a, const FACE_COUNT: usize> Handle<'a, Overlay<FACE_COUNT>> {
+    /// Get the texture of the overlay
+    pub fn texture(&self) -> Handle<'a, TextureInfo> {
+        self.bsp.texture_info(self.texture_info as usize).unwrap()
+    }
+
+    /// Get all faces the overlay is applied to
+    pub fn faces(&self) -> impl Iterator<Item = Handle<'a, Face>> + use<'a, FACE_COUNT> {
+        let bsp = self.bsp;
+        self.data
+            .face_indexes()
+            .iter()
+            .filter_map(move |face| bsp.face(*face as usize))
+    }
+
+    /// Project the overlay onto the faces it's applied to and triangulate the result
+    ///
+    /// Overlays on displacements are projected onto the undisplaced face
+    pub fn triangulate(&self) -> impl Iterator<Item = [OverlayVertex; 3]> + use<'a, FACE_COUNT> {
+        let overlay = self.data;
+        self.faces().flat_map(move |face| {
+            let polygon = overlay.project(face.vertices().map(|vertex| vertex.position));
+            let a = polygon.first().copied();
+            polygon
+                .windows(2)
+                .skip(1)
+                .filter_map(move |window| a.map(|a| [window[1], window[0], a]))
+                .collect::<Vec<_>>()
+        })
+    }
+}
+
+impl<'a> Handle<'a, Overlay> {
+    /// Get the distances at which the overlay fades out
+    pub fn fade(&self) -> Option<&'a OverlayFade> {
+        self.bsp
+            .overlay_fades
+            .get(self.index_in(&self.bsp.overlays))
+    }
+
+    /// Get the cpu and gpu levels at which the overlay is rendered
+    pub fn system_level(&self) -> Option<&'a OverlaySystemLevel> {
+        self.bsp
+            .overlay_system_levels
+            .get(self.index_in(&self.bsp.overlays))
+    }
+}
@@ -1,6 +1,7 @@
 mod bspfile;
 pub mod data;
 pub mod error;
+mod filesystem;
 mod handle;
 mod reader;
 
@@ -8,11 +9,12 @@ use crate::bspfile::LumpType;
 pub use crate::data::TextureFlags;
 pub use crate::data::*;
 use crate::error::ValidationError;
-pub use crate::handle::Handle;
+pub use crate::handle::{Handle, ModelPlacement};
 use binrw::io::Cursor;
 use binrw::{BinRead, BinReaderExt};
 use bspfile::BspFile;
 pub use error::{BspError, StringError};
+pub use filesystem::{FileSource, FileSystem};
 use lzma_rs::decompress::{Options, UnpackedSize};
 use reader::LumpReader;
 use std::cmp::min;
@@ -53,6 +55,19 @@ pub struct Bsp {
     vertex_normal_indices: Vec<VertNormalIndex>,
     pub static_props: PropStaticGameLump,
     pub pack: Packfile,
+    pub overlays: Vec<Overlay>,
+    pub water_overlays: Vec<WaterOverlay>,
+    pub overlay_fades: Vec<OverlayFade>,
+    pub overlay_system_levels: Vec<OverlaySystemLevel>,
+    pub cubemaps: Vec<CubeMapSample>,
+    pub leaf_water_data: Vec<LeafWaterData>,
+    pub leaf_minimum_distance_to_water: Vec<u16>,
+    pub phys_collide: PhysCollideLump,
+    pub phys_displacements: PhysDisplacementLump,
+    pub occlusion: OcclusionLump,
+    pub primitives: Vec<Primitive>,
+    pub primitive_vertices: Vec<PrimitiveVertex>,
+    pub primitive_indices: Vec<u16>,
 }
 
 impl Bsp {
@@ -130,6 +145,41 @@ impl Bsp {
             .read_vec(|r| r.read())?;
         let game_lumps: GameLumpHeader = bsp_file.lump_reader(LumpType::GameLump)?.read()?;
         let pack = Packfile::read(bsp_file.lump_reader(LumpType::PakFile)?.into_data())?;
+        let overlays = bsp_file
+            .lump_reader(LumpType::Overlays)?
+            .read_vec(|r| r.read())?;
+        let water_overlays = bsp_file
+            .lump_reader(LumpType::WaterOverlays)?
+            .read_vec(|r| r.read())?;
+        let overlay_fades = bsp_file
+            .lump_reader(LumpType::OverlayFades)?
+            .read_vec(|r| r.read())?;
+        let overlay_system_levels = bsp_file
+            .lump_reader(LumpType::OverlaySystemLevels)?
+            .read_vec(|r| r.read())?;
+        let cubemaps = bsp_file
+            .lump_reader(LumpType::CubeMaps)?
+            .read_vec(|r| r.read())?;
+        let leaf_water_data = bsp_file
+            .lump_reader(LumpType::LeafWaterData)?
+            .read_vec(|r| r.read())?;
+        let leaf_minimum_distance_to_water = bsp_file
+            .lump_reader(LumpType::LeafMinimumDistanceToWater)?
+            .read_vec(|r| r.read())?;
+        let phys_collide = bsp_file.lump_reader(LumpType::PhysCollide)?.read_args()?;
+        let phys_displacements = bsp_file
+            .lump_reader(LumpType::PhysDisplacement)?
+            .read_args()?;
+        let occlusion = bsp_file.lump_reader(LumpType::Occlusion)?.read_args()?;
+        let primitives = bsp_file
+            .lump_reader(LumpType::Primitives)?
+            .read_vec(|r| r.read())?;
+        let primitive_vertices = bsp_file
+            .lump_reader(LumpType::PrimVertices)?
+            .read_vec(|r| r.read())?;
+        let primitive_indices = bsp_file
+            .lump_reader(LumpType::PrimIndices)?
+            .read_vec(|r| r.read())?;
 
         let static_props = game_lumps
             .find(data)
@@ -163,6 +213,19 @@ impl Bsp {
             vertex_normal_indices,
             static_props,
             pack,
+            overlays,
+            water_overlays,
+            overlay_fades,
+            overlay_system_levels,
+            cubemaps,
+            leaf_water_data,
+            leaf_minimum_distance_to_water,
+            phys_collide,
+            phys_displacements,
+            occlusion,
+            primitives,
+            primitive_vertices,
+            primitive_indices,
         };
         bsp.validate()?;
         Ok(bsp)
@@ -202,6 +265,12 @@ impl Bsp {
             .map(|vert| Handle::new(self, vert))
     }
 
+    fn displacement_triangle(&self, n: usize) -> Option<Handle<'_, DisplacementTriangle>> {
+        self.displacement_triangles
+            .get(n)
+            .map(|triangle| Handle::new(self, triangle))
+    }
+
     /// Get the root node of the bsp
     pub fn root_node(&self) -> Handle<'_, Node> {
         self.node(0).unwrap()
@@ -212,6 +281,21 @@ impl Bsp {
         self.models.iter().map(move |m| Handle::new(self, m))
     }
 
+    /// Get the brush model used by an entity with a `"model" "*N"` property
+    pub fn entity_model(&self, entity: &RawEntity) -> Option<Handle<'_, Model>> {
+        self.models
+            .get(entity.brush_model_index()?)
+            .map(|model| Handle::new(self, model))
+    }
+
+    /// Get the brush model used by an entity, together with the entity's world transform
+    pub fn entity_model_placement(&self, entity: &RawEntity) -> Option<ModelPlacement<'_>> {
+        Some(ModelPlacement {
+            model: self.entity_model(entity)?,
+            transform: entity.transform(),
+        })
+    }
+
     /// Get all models stored in the bsp
     pub fn textures(&self) -> impl Iterator<Item = Handle<'_, TextureInfo>> {
         self.textures_info.iter().map(move |m| Handle::new(self, m))
@@ -241,6 +325,13 @@ impl Bsp {
         }
     }
 
+    /// Find the water volume at a specific position
+    ///
+    /// Returns `None` if the position is not in water
+    pub fn water_at(&self, point: Vector) -> Option<Handle<'_, LeafWaterData>> {
+        self.leaf_at(point).water_data()
+    }
+
     pub fn static_props(&self) -> impl Iterator<Item = Handle<'_, StaticPropLump>> {
         self.static_props
             .props
@@ -249,6 +340,75 @@ impl Bsp {
             .map(|lump| Handle::new(self, lump))
     }
 
+    /// Get the placements of all props in the map, both static props and prop entities
+    pub fn prop_placements(&self) -> impl Iterator<Item = PropPlacement<'_>> {
+        self.static_props()
+            .map(|prop| prop.as_prop_placement())
+            .chain(
+                self.entities
+                    .iter()
+                    .filter_map(PropEntity::new)
+                    .map(|prop| prop.as_prop_placement()),
+            )
+    }
+
+    /// Get all static props that are potentially visible from a specific position
+    ///
+    /// Returns `None` if the position is outside the map
+    pub fn visible_static_props(
+        &self,
+        point: Vector,
+    ) -> Option<impl Iterator<Item = Handle<'_, StaticPropLump>>> {
+        self.leaf_at(point).visible_static_props()
+    }
+
+    /// Get all overlays stored in the bsp
+    pub fn overlays(&self) -> impl Iterator<Item = Handle<'_, Overlay>> {
+        self.overlays
+            .iter()
+            .map(|overlay| Handle::new(self, overlay))
+    }
+
+    /// Get all water overlays stored in the bsp
+    pub fn water_overlays(&self) -> impl Iterator<Item = Handle<'_, WaterOverlay>> {
+        self.water_overlays
+            .iter()
+            .map(|overlay| Handle::new(self, overlay))
+    }
+
+    /// Get all cubemap samples stored in the bsp
+    pub fn cubemaps(&self) -> impl Iterator<Item = Handle<'_, CubeMapSample>> {
+        self.cubemaps
+            .iter()
+            .map(|cubemap| Handle::new(self, cubemap))
+    }
+
+    /// Find the cubemap sample closest to a specific position
+    pub fn nearest_cubemap(&self, point: Vector) -> Option<Handle<'_, CubeMapSample>> {
+        self.cubemaps().min_by(|a, b| {
+            (a.origin() - point)
+                .length_squared()
+                .total_cmp(&(b.origin() - point).length_squared())
+        })
+    }
+
+    /// Get all occluders stored in the bsp
+    pub fn occluders(&self) -> impl Iterator<Item = Handle<'_, Occluder>> {
+        self.occlusion
+            .occluders
+            .iter()
+            .map(|occluder| Handle::new(self, occluder))
+    }
+
+    /// Create a filesystem that looks up files in the packfile of the map first
+    ///
+    /// Game directories and vpk archives can be added to the filesystem to look up files not embedded in the map
+    pub fn file_system(&self) -> FileSystem {
+        let mut fs = FileSystem::new();
+        fs.add_pack(self.pack.clone());
+        fs
+    }
+
     /// Get all faces stored in the bsp
     pub fn original_faces(&self) -> impl Iterator<Item = Handle<Face>> {
         self.faces.iter().map(move |face| Handle::new(self, face))
@@ -370,6 +530,20 @@ impl Bsp {
             "static props",
             "static prop models",
         )?;
+        self.validate_indexes(
+            self.static_props()
+                .filter(|prop| prop.leaf_count > 0)
+                .map(|prop| prop.first_leaf as i32 + prop.leaf_count as i32 - 1),
+            &self.static_props.leaf.leaves,
+            "static props",
+            "static prop leaves",
+        )?;
+        self.validate_indexes(
+            self.static_props.leaf.leaves.iter().copied(),
+            &self.leaves,
+            "static prop leaves",
+            "leaf",
+        )?;
         self.validate_indexes(
             self.vertex_normal_indices.iter().map(|i| i.index),
             &self.vertex_normals,
@@ -377,6 +551,127 @@ impl Bsp {
             "vertex normals",
         )?;
 
+        self.validate_indexes(
+            self.overlays.iter().map(|overlay| overlay.texture_info),
+            &self.textures_info,
+            "overlay",
+            "texture_info",
+        )?;
+        self.validate_indexes(
+            self.overlays
+                .iter()
+                .flat_map(|overlay| overlay.face_indexes().iter().copied()),
+            &self.faces,
+            "overlay",
+            "face",
+        )?;
+        self.validate_indexes(
+            self.water_overlays
+                .iter()
+                .map(|overlay| overlay.texture_info),
+            &self.textures_info,
+            "water overlay",
+            "texture_info",
+        )?;
+        self.validate_indexes(
+            self.water_overlays
+                .iter()
+                .flat_map(|overlay| overlay.face_indexes().iter().copied()),
+            &self.faces,
+            "water overlay",
+            "face",
+        )?;
+
+        self.validate_indexes(
+            self.leaves
+                .iter()
+                .map(|leaf| leaf.leaf_watter_data_id)
+                .filter(|index| *index >= 0),
+            &self.leaf_water_data,
+            "leaf",
+            "leaf water data",
+        )?;
+        self.validate_indexes(
+            self.leaf_water_data
+                .iter()
+                .map(|water| water.surface_texture_info)
+                .filter(|index| *index >= 0),
+            &self.textures_info,
+            "leaf water data",
+            "texture_info",
+        )?;
+
+        self.validate_indexes(
+            self.phys_collide
+                .models
+                .iter()
+                .map(|model| model.model_index),
+            &self.models,
+            "physics model",
+            "model",
+        )?;
+
+        self.validate_indexes(
+            self.occlusion
+                .occluders
+                .iter()
+                .filter(|occluder| occluder.polygon_count > 0)
+                .map(|occluder| occluder.first_polygon + occluder.polygon_count - 1),
+            &self.occlusion.polygons,
+            "occluder",
+            "occluder polygon",
+        )?;
+        self.validate_indexes(
+            self.occlusion
+                .polygons
+                .iter()
+                .filter(|polygon| polygon.vertex_count > 0)
+                .map(|polygon| polygon.first_vertex_index + polygon.vertex_count - 1),
+            &self.occlusion.vertex_indices,
+            "occluder polygon",
+            "occluder vertex index",
+        )?;
+        self.validate_indexes(
+            self.occlusion.polygons.iter().map(|polygon| polygon.plane),
+            &self.planes,
+            "occluder polygon",
+            "plane",
+        )?;
+        self.validate_indexes(
+            self.occlusion.vertex_indices.iter().copied(),
+            &self.vertices,
+            "occluder vertex index",
+            "vertex",
+        )?;
+
+        self.validate_indexes(
+            self.faces
+                .iter()
+                .filter(|face| face.primitive_count() > 0)
+                .map(|face| face.first_primitive_index as i32 + face.primitive_count() as i32 - 1),
+            &self.primitives,
+            "face",
+            "primitive",
+        )?;
+        self.validate_indexes(
+            self.primitives
+                .iter()
+                .filter(|primitive| primitive.index_count > 0)
+                .map(|primitive| primitive.first_index as i32 + primitive.index_count as i32 - 1),
+            &self.primitive_indices,
+            "primitive",
+            "primitive index",
+        )?;
+        self.validate_indexes(
+            self.primitives
+                .iter()
+                .filter(|primitive| primitive.vertex_count > 0)
+                .map(|primitive| primitive.first_vertex as i32 + primitive.vertex_count as i32 - 1),
+            &self.primitive_vertices,
+            "primitive",
+            "primitive vertex",
+        )?;
+
         if self.nodes.is_empty() {
             return Err(ValidationError::NoRootNode.into());
         }
@@ -468,4 +763,32 @@ mod tests {
 
         Bsp::read(&data).unwrap();
     }
+
+    #[test]
+    fn tf2_file_static_prop_leaves() {
+        use std::fs::read;
+
+        let data = read("koth_bagel_rc2a.bsp").unwrap();
+        let bsp = Bsp::read(&data).unwrap();
+
+        let mut checked = 0;
+        let mut matched = 0;
+        for prop in bsp.static_props() {
+            for leaf in prop.leaves() {
+                assert!(leaf
+                    .static_props()
+                    .any(|found| found.index() == prop.index()));
+            }
+            // the origin of a prop is usually, but not always, inside one of its leaves
+            let origin_leaf = bsp.leaf_at(prop.origin);
+            if origin_leaf.cluster >= 0 {
+                checked += 1;
+                if prop.leaf_indexes().contains(&(origin_leaf.index() as u16)) {
+                    matched += 1;
+                }
+            }
+        }
+        assert!(checked > 0);
+        assert!(matched * 2 > checked);
+    }
 }